@@ -1,9 +1,15 @@
 use std::env;
+use std::error::Error as StdError;
+use std::io::{self, BufRead, Write};
+use std::process;
 
 use clap::{App, Arg, ArgMatches};
 use colored::*;
 
-use cargo_wsinit::{Error, FileExistsBehaviour, Options, Workspace};
+use cargo_wsinit::{
+    Action, Error, FileExistsBehaviour, MissingMemberBehaviour, NestedWorkspaceBehaviour, Options,
+    Resolver, SortOrder, TomlChanges, Workspace,
+};
 
 macro_rules! wsinit {
     () => {
@@ -31,6 +37,152 @@ fn main() {
                 .help("Update the workspace toml file. It must exist.")
                 .conflicts_with("overwrite"),
         )
+        .arg(
+            Arg::with_name("check")
+                .long("check")
+                .help("Report what --update would change without writing. Fails if the file is out of date.")
+                .conflicts_with("overwrite"),
+        )
+        .arg(
+            Arg::with_name("backup")
+                .long("backup")
+                .help("Copy the workspace toml file to Cargo.toml.bak before replacing it"),
+        )
+        .arg(
+            Arg::with_name("undo")
+                .long("undo")
+                .conflicts_with_all(&["overwrite", "update", "check", "backup"])
+                .help("Restore the workspace toml file from Cargo.toml.bak"),
+        )
+        .arg(
+            Arg::with_name("missing")
+                .long("missing")
+                .takes_value(true)
+                .possible_values(&["keep", "comment", "remove"])
+                .default_value("keep")
+                .help("What --update does with members whose crate no longer exists"),
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .long("exclude")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("PATH|GLOB")
+                .help("Exclude a path from the workspace. Recorded in the toml file's exclude list."),
+        )
+        .arg(
+            Arg::with_name("nested")
+                .long("nested")
+                .takes_value(true)
+                .possible_values(&["skip", "absorb", "exclude"])
+                .default_value("skip")
+                .help("What to do with crates that are the root of a workspace of their own"),
+        )
+        .arg(
+            Arg::with_name("move-root-package")
+                .long("move-root-package")
+                .takes_value(true)
+                .value_name("DIR")
+                .conflicts_with("overwrite")
+                .help("Move the package in the root toml file into DIR, leaving a virtual manifest. Implies --update."),
+        )
+        .arg(
+            Arg::with_name("hoist-deps")
+                .long("hoist-deps")
+                .help("Move dependencies shared by members into [workspace.dependencies] and have the members inherit them"),
+        )
+        .arg(
+            Arg::with_name("path-deps")
+                .long("path-deps")
+                .help("Move path dependencies between members into [workspace.dependencies] and have the members inherit them"),
+        )
+        .arg(
+            Arg::with_name("inherit-package")
+                .long("inherit-package")
+                .help("Move package fields, like edition and license, that all members share into [workspace.package] and have the members inherit them"),
+        )
+        .arg(
+            Arg::with_name("profiles")
+                .long("profiles")
+                .help("Move [profile] settings from members into the workspace toml file, showing the changes and asking first"),
+        )
+        .arg(
+            Arg::with_name("patches")
+                .long("patches")
+                .help("Move [patch] and [replace] tables from members into the workspace toml file"),
+        )
+        .arg(
+            Arg::with_name("merge-lockfiles")
+                .long("merge-lockfiles")
+                .help("Merge the members' Cargo.lock files into the workspace Cargo.lock and delete them"),
+        )
+        .arg(
+            Arg::with_name("resolver")
+                .long("resolver")
+                .takes_value(true)
+                .possible_values(&["1", "2", "3"])
+                .help("Set the workspace resolver instead of picking it from the members' editions"),
+        )
+        .arg(
+            Arg::with_name("clean-member-targets")
+                .long("clean-member-targets")
+                .conflicts_with("check")
+                .help("Delete the target directories members built on their own, after asking"),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Move profiles and delete member target directories without asking"),
+        )
+        .arg(
+            Arg::with_name("globs")
+                .long("globs")
+                .help("Write members as a dir/* glob when every entry in that directory is a member"),
+        )
+        .arg(
+            Arg::with_name("max-depth")
+                .long("max-depth")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|depth| depth.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Only look for crates up to N directories below the workspace root"),
+        )
+        .arg(
+            Arg::with_name("no-follow-symlinks")
+                .long("no-follow-symlinks")
+                .help("Do not follow symbolic links to directories when looking for crates"),
+        )
+        .arg(
+            Arg::with_name("append")
+                .long("append")
+                .help("Add new members after the existing ones instead of in sorted position"),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .conflicts_with_all(&["check", "undo", "clean-member-targets"])
+                .help("Show what would change without writing anything"),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .help("Fail if any directory could not be searched for crates"),
+        )
+        .arg(
+            Arg::with_name("no-ignore")
+                .long("no-ignore")
+                .help("Search directories listed in .gitignore, .ignore and git exclude files"),
+        )
+        .arg(
+            Arg::with_name("message-format")
+                .long("message-format")
+                .takes_value(true)
+                .possible_values(&["human", "json"])
+                .default_value("human")
+                .help("Print coloured messages, or one JSON report"),
+        )
         .arg(
             Arg::with_name("path")
                 .short("p")
@@ -49,63 +201,280 @@ fn main() {
     let path = matches.value_of("path").expect("Has default");
     let file_exists_behaviour = get_file_exists_behaviour(&matches);
 
-    let options = Options::new(path, file_exists_behaviour);
-    let workspace = Workspace::new(options);
-    match workspace.update_toml() {
-        Ok(toml) => {
-            println!(
-                "{}",
-                format!("Workspace file created/updated at {}", toml).green()
-            );
+    let mut options = Options::builder(path)
+        .existing_file_behaviour(file_exists_behaviour)
+        .respect_ignore_files(!matches.is_present("no-ignore"))
+        .follow_symlinks(!matches.is_present("no-follow-symlinks"))
+        .sort_order(get_sort_order(&matches))
+        .dry_run(matches.is_present("dry-run"))
+        .strict(matches.is_present("strict"))
+        .missing_member_behaviour(get_missing_member_behaviour(&matches))
+        .collapse_globs(matches.is_present("globs"))
+        .hoist_dependencies(matches.is_present("hoist-deps"))
+        .workspace_path_dependencies(matches.is_present("path-deps"))
+        .inherit_package_fields(matches.is_present("inherit-package"))
+        .consolidate_profiles(matches.is_present("profiles"))
+        .consolidate_patches(matches.is_present("patches"))
+        .merge_lockfiles(matches.is_present("merge-lockfiles"))
+        .backup(matches.is_present("backup"))
+        .nested_workspace_behaviour(get_nested_workspace_behaviour(&matches));
+    if let Some(resolver) = get_resolver(&matches) {
+        options = options.resolver(resolver);
+    }
+    if let Some(dir) = matches.value_of("move-root-package") {
+        options = options.move_root_package(dir);
+    }
+    if let Some(depth) = matches.value_of("max-depth") {
+        options = options.max_depth(depth.parse().expect("Validated"));
+    }
+    for exclude in matches.values_of("exclude").into_iter().flatten() {
+        options = options.exclude(exclude);
+    }
+    let workspace = match options.build() {
+        Ok(options) => Workspace::new(options),
+        Err(err) => {
+            eprintln!("{}", format!("Invalid options: {}!", err).red());
+            process::exit(1);
         }
-        Err(Error::FileAlreadyExists) => {
+    };
+
+    let asks_first = matches.is_present("profiles")
+        && !matches.is_present("dry-run")
+        && !matches.is_present("yes");
+
+    if matches.value_of("message-format") == Some("json") {
+        if asks_first {
             eprintln!(
                 "{}",
-                format!(
-                    "Could not create file {} for workspace!\nThe file already exists. Use --update to replace.",
-                    workspace.toml()
-                ).red()
+                "Error: --profiles with --message-format json needs --dry-run or --yes!".red()
             );
+            process::exit(1);
         }
-        Err(Error::GenericCreationError(io)) => {
+        if matches.is_present("clean-member-targets") && !matches.is_present("yes") {
             eprintln!(
                 "{}",
-                format!(
-                    "Could not create file {} for workspace!\n{:?}",
-                    workspace.toml(),
-                    io.kind()
-                )
-                .red()
+                "Error: --clean-member-targets with --message-format json needs --yes!".red()
             );
+            process::exit(1);
         }
-        Err(Error::WriteError(io)) => {
-            eprintln!(
+        report(&workspace, &matches);
+        return;
+    }
+
+    if matches.is_present("check") {
+        check(&workspace);
+        return;
+    }
+
+    if matches.is_present("undo") {
+        match workspace.undo() {
+            Ok(toml) => println!("{}", format!("Workspace file restored at {}", toml).green()),
+            Err(err) => {
+                print_error(&workspace, err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if asks_first && preview(&workspace) && !confirm("Write these changes? [y/N] ") {
+        println!("{}", "Nothing was written".yellow());
+        return;
+    }
+
+    match workspace.update() {
+        Ok(changes) if matches.is_present("dry-run") => {
+            print!("{}", changes.diff());
+            print_warnings(&changes);
+            println!(
                 "{}",
-                format!(
-                    "Could not write file {} for workspace!\n{:?}",
-                    workspace.toml(),
-                    io
-                )
-                .red()
+                format!("Dry run, {} was not written", changes.toml()).yellow()
             );
         }
-        Err(Error::ReadError(io)) => {
+        Ok(changes) => {
+            print_warnings(&changes);
+            for change in changes.manifest_changes() {
+                match change.proposed() {
+                    Some(_) => println!("Wrote {}", change.toml()),
+                    None => println!("Deleted {}", change.toml()),
+                }
+            }
+            if changes.written() {
+                println!(
+                    "{}",
+                    format!("Workspace file created/updated at {}", changes.toml()).green()
+                );
+            } else {
+                println!(
+                    "{}",
+                    format!("Workspace file {} is unchanged", changes.toml()).green()
+                );
+            }
+            if matches.is_present("clean-member-targets") {
+                clean_member_targets(&workspace, matches.is_present("yes"));
+            }
+        }
+        Err(err) => {
+            print_error(&workspace, err);
+            process::exit(1);
+        }
+    }
+}
+
+fn clean_member_targets(workspace: &Workspace, yes: bool) {
+    let targets = match workspace.stale_targets() {
+        Ok(targets) => targets,
+        Err(err) => {
+            print_error(workspace, err);
+            process::exit(1);
+        }
+    };
+    if targets.is_empty() {
+        return;
+    }
+
+    for target in &targets {
+        println!("{}", target);
+    }
+
+    if !yes && !confirm("Delete these directories? [y/N] ") {
+        return;
+    }
+
+    let mut failed = false;
+    for target in targets {
+        match target.remove() {
+            Ok(()) => println!("Deleted {}", target.path().display()),
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    format!("Could not delete {}!\n{}", target.path().display(), err).red()
+                );
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Asks on stdin, treating anything but yes as no.
+fn confirm(question: &str) -> bool {
+    print!("{}", question);
+    if io::stdout().flush().is_err() {
+        return false;
+    }
+
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+fn check(workspace: &Workspace) {
+    let changes = workspace.check_toml();
+    if let Ok(changes) = &changes {
+        print_warnings(changes);
+    }
+
+    match changes {
+        Ok(changes) if changes.is_stale() => {
+            print!("{}", changes.diff());
+            for member in changes.added() {
+                println!("{}", format!("+ {}", member).green());
+            }
+            for member in changes.removed() {
+                println!("{}", format!("- {}", member).red());
+            }
             eprintln!(
                 "{}",
-                format!("Could not read file {}!\n{:?}", workspace.toml(), io).red()
+                format!("Workspace file {} is out of date!", changes.toml()).red()
             );
+            process::exit(1);
         }
-        Err(Error::ParseError) => {
-            eprintln!(
+        Ok(changes) => {
+            println!(
                 "{}",
-                format!(
-                    "Could not parse the existing toml file {}!",
-                    workspace.toml()
-                )
-                .red()
+                format!("Workspace file {} is up to date", changes.toml()).green()
             );
         }
+        Err(err) => {
+            print_error(workspace, err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Prints the JSON report of an update, check or undo, failing like the coloured output would.
+fn report(workspace: &Workspace, matches: &ArgMatches) {
+    let check = matches.is_present("check");
+    let report = if check {
+        workspace.check_report()
+    } else if matches.is_present("undo") {
+        workspace.undo_report()
+    } else if matches.is_present("clean-member-targets") {
+        workspace.clean_targets_report(workspace.update_report())
+    } else {
+        workspace.update_report()
+    };
+    println!("{}", report.to_json());
+
+    let stale = check && report.action() != Some(Action::Unchanged);
+    if report.error().is_some() || stale {
+        process::exit(1);
+    }
+}
+
+/// Shows what is about to be written, returning whether there is anything to write.
+fn preview(workspace: &Workspace) -> bool {
+    match workspace.check_toml() {
+        Ok(changes) if changes.is_stale() => {
+            print!("{}", changes.diff());
+            true
+        }
+        Ok(_) => false,
+        Err(err) => {
+            print_error(workspace, err);
+            process::exit(1);
+        }
+    }
+}
+
+fn print_warnings(changes: &TomlChanges) {
+    for skipped in changes.skipped() {
+        eprintln!("{}", format!("Skipped {}", skipped).yellow());
+    }
+    for warning in changes.discovery_warnings() {
+        eprintln!("{}", format!("Could not search {}", warning).yellow());
+    }
+    for conflict in changes.conflicts() {
+        eprintln!("{}", format!("Left in members {}", conflict).yellow());
+    }
+    for dependency in changes.external_path_dependencies() {
+        eprintln!("{}", format!("Path dependency {}", dependency).yellow());
+    }
+    if let Some(mismatch) = changes.resolver_mismatch() {
+        eprintln!("{}", format!("Workspace {}", mismatch).yellow());
+    }
+}
+
+fn print_error(workspace: &Workspace, err: Error) {
+    let mut message = format!("Error: {}!", err);
+
+    // the cause of a parse error shows the line and column, with a snippet
+    if let Some(source) = err.source() {
+        message.push_str(&format!("\n{}", source));
+    }
+
+    if let Error::FileAlreadyExists { path } = &err {
+        if path == workspace.toml().as_path() {
+            message.push_str("\nUse --update to replace.");
+        }
     }
+
+    eprintln!("{}", message.red());
 }
 
 #[derive(PartialEq)]
@@ -128,7 +497,10 @@ fn get_command_line_args(cargo_sub_command: &str) -> (Vec<String>, CargoRun) {
 }
 
 fn get_file_exists_behaviour(matches: &ArgMatches) -> FileExistsBehaviour {
-    if matches.is_present("update") {
+    if matches.is_present("update")
+        || matches.is_present("check")
+        || matches.is_present("move-root-package")
+    {
         FileExistsBehaviour::Update
     } else if matches.is_present("overwrite") {
         FileExistsBehaviour::Overwrite
@@ -136,3 +508,36 @@ fn get_file_exists_behaviour(matches: &ArgMatches) -> FileExistsBehaviour {
         FileExistsBehaviour::Halt
     }
 }
+
+fn get_missing_member_behaviour(matches: &ArgMatches) -> MissingMemberBehaviour {
+    match matches.value_of("missing") {
+        Some("comment") => MissingMemberBehaviour::CommentOut,
+        Some("remove") => MissingMemberBehaviour::Remove,
+        _ => MissingMemberBehaviour::Keep,
+    }
+}
+
+fn get_nested_workspace_behaviour(matches: &ArgMatches) -> NestedWorkspaceBehaviour {
+    match matches.value_of("nested") {
+        Some("absorb") => NestedWorkspaceBehaviour::Absorb,
+        Some("exclude") => NestedWorkspaceBehaviour::Exclude,
+        _ => NestedWorkspaceBehaviour::Skip,
+    }
+}
+
+fn get_sort_order(matches: &ArgMatches) -> SortOrder {
+    if matches.is_present("append") {
+        SortOrder::Append
+    } else {
+        SortOrder::Sorted
+    }
+}
+
+fn get_resolver(matches: &ArgMatches) -> Option<Resolver> {
+    match matches.value_of("resolver") {
+        Some("1") => Some(Resolver::V1),
+        Some("2") => Some(Resolver::V2),
+        Some("3") => Some(Resolver::V3),
+        _ => None,
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A setting that members declare differently, so it was left where it is rather than moved to
+/// the workspace root.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    key: String,
+    values: Vec<(String, String)>,
+}
+
+impl Conflict {
+    pub(crate) fn new(key: String, values: Vec<(String, String)>) -> Conflict {
+        Conflict { key, values }
+    }
+
+    /// The dotted toml key in question, e.g. `dependencies.serde`.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Each member, or `workspace` for the root toml file, with the value it declares.
+    pub fn values(&self) -> &[(String, String)] {
+        &self.values
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}:", self.key)?;
+
+        for (index, (member, value)) in self.values.iter().enumerate() {
+            let separator = if index == 0 { " " } else { ", " };
+            write!(f, "{}{} has {}", separator, member, value)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::{Path, PathBuf};
+
+/// A path below the workspace root that could not be searched for crates. Any crates inside it
+/// are missing from the members.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiscoveryWarning {
+    path: PathBuf,
+    message: String,
+}
+
+impl DiscoveryWarning {
+    pub(crate) fn new(path: PathBuf, message: String) -> DiscoveryWarning {
+        DiscoveryWarning { path, message }
+    }
+
+    /// The path, relative to the workspace root.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Why the path could not be read, e.g. permission denied.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for DiscoveryWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
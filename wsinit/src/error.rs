@@ -0,0 +1,160 @@
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::Error as IoError;
+use std::path::{Path, PathBuf};
+
+use toml_edit::TomlError;
+
+use crate::conflict::Conflict;
+use crate::discovery_warning::DiscoveryWarning;
+
+/// Why creating, updating or checking a workspace failed, with the path involved.
+#[derive(Debug)]
+pub enum Error {
+    /// The file exists, and the existing file behaviour does not replace it.
+    FileAlreadyExists { path: PathBuf },
+
+    /// There is no backup to restore.
+    NoBackup { path: PathBuf },
+
+    /// The toml file has no package to move.
+    NoRootPackage { path: PathBuf },
+
+    /// Members declare the same patch differently, so the patches could not move into the toml
+    /// file.
+    ConflictingPatch { path: PathBuf, conflict: Conflict },
+
+    /// Searching, reading or writing the file system failed.
+    Io {
+        operation: Operation,
+        path: PathBuf,
+        source: IoError,
+    },
+
+    /// A file is not valid toml. The cause gives the line and column, with a snippet.
+    Parse { path: PathBuf, source: TomlError },
+
+    /// Paths below the workspace root could not be searched, and
+    /// [`strict`](crate::OptionsBuilder::strict) is set.
+    Unsearched {
+        path: PathBuf,
+        warnings: Vec<DiscoveryWarning>,
+    },
+}
+
+/// What was being done to a path when an [`Error`] happened.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    /// Searching a directory for crates.
+    Discover,
+    Read,
+    Parse,
+    Write,
+}
+
+impl Error {
+    /// Wraps an io error from the operation on the path.
+    pub(crate) fn io(operation: Operation, path: &Path) -> impl FnOnce(IoError) -> Error {
+        let path = path.to_path_buf();
+        move |source| Error::Io {
+            operation,
+            path,
+            source,
+        }
+    }
+
+    /// Wraps an error parsing the file at the path.
+    pub(crate) fn parse(path: &Path) -> impl FnOnce(TomlError) -> Error {
+        let path = path.to_path_buf();
+        move |source| Error::Parse { path, source }
+    }
+
+    /// The file or directory the error is about.
+    pub fn path(&self) -> &Path {
+        match self {
+            Error::FileAlreadyExists { path }
+            | Error::NoBackup { path }
+            | Error::NoRootPackage { path }
+            | Error::ConflictingPatch { path, .. }
+            | Error::Io { path, .. }
+            | Error::Parse { path, .. }
+            | Error::Unsearched { path, .. } => path,
+        }
+    }
+
+    /// What was being done to the path, for errors with an underlying cause.
+    pub fn operation(&self) -> Option<Operation> {
+        match self {
+            Error::Io { operation, .. } => Some(*operation),
+            Error::Parse { .. } => Some(Operation::Parse),
+            Error::Unsearched { .. } => Some(Operation::Discover),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Error::FileAlreadyExists { path } => write!(f, "{} already exists", path.display()),
+            Error::NoBackup { path } => {
+                write!(f, "there is no backup {} to restore", path.display())
+            }
+            Error::NoRootPackage { path } => {
+                write!(f, "{} has no package to move", path.display())
+            }
+            Error::ConflictingPatch { path, conflict } => write!(
+                f,
+                "could not move patches into {}, members disagree on {}",
+                path.display(),
+                conflict
+            ),
+            Error::Io {
+                operation, path, ..
+            } => match operation {
+                Operation::Discover => {
+                    write!(f, "could not search {} for crates", path.display())
+                }
+                operation => write!(f, "could not {} {}", operation, path.display()),
+            },
+            Error::Parse { path, .. } => write!(f, "could not parse {}", path.display()),
+            Error::Unsearched { path, warnings } => {
+                write!(f, "could not search all of {}", path.display())?;
+
+                for (index, warning) in warnings.iter().enumerate() {
+                    let separator = if index == 0 { ", " } else { "; " };
+                    write!(f, "{}{}", separator, warning)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Discover => "discover",
+            Operation::Read => "read",
+            Operation::Parse => "parse",
+            Operation::Write => "write",
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.as_str())
+    }
+}
@@ -0,0 +1,39 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A member's path dependency that points outside the workspace root.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalPathDependency {
+    member: String,
+    name: String,
+    path: String,
+}
+
+impl ExternalPathDependency {
+    pub(crate) fn new(member: String, name: String, path: String) -> ExternalPathDependency {
+        ExternalPathDependency { member, name, path }
+    }
+
+    /// The member declaring the dependency, relative to the workspace root.
+    pub fn member(&self) -> &str {
+        &self.member
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The path as written in the member's manifest.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Display for ExternalPathDependency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{}: {} at {} is outside the workspace root",
+            self.member, self.name, self.path
+        )
+    }
+}
@@ -0,0 +1,464 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::fs::{self, OpenOptions};
+use std::io::prelude::*;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use ignore::{Error as WalkError, WalkBuilder};
+
+/// Decides whether a walk goes into a directory.
+pub type EnterDir = Arc<dyn Fn(&Path) -> bool + Send + Sync>;
+
+/// Numbers the temporary files written in this process, so concurrent writes never share one.
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// The files a walk found, and the paths below the root it could not read.
+pub type Walked = (Vec<PathBuf>, Vec<(PathBuf, IoError)>);
+
+/// The file operations a [`Workspace`](crate::Workspace) makes, so it can work on something
+/// other than the disk. Set with
+/// [`OptionsBuilder::file_system`](crate::OptionsBuilder::file_system).
+pub trait FileSystem: Debug + Send + Sync {
+    fn read_to_string(&self, path: &Path) -> Result<String, IoError>;
+
+    /// Creates or replaces a file. The parent directory must exist.
+    fn write(&self, path: &Path, contents: &str) -> Result<(), IoError>;
+
+    fn remove_file(&self, path: &Path) -> Result<(), IoError>;
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), IoError>;
+
+    /// Moves a file or directory.
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), IoError>;
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<(), IoError>;
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), IoError>;
+
+    /// The paths of the entries in a directory.
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, IoError>;
+
+    /// The size of a file in bytes.
+    fn file_size(&self, path: &Path) -> Result<u64, IoError>;
+
+    fn is_file(&self, path: &Path) -> bool;
+
+    fn is_dir(&self, path: &Path) -> bool;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn is_symlink(&self, _path: &Path) -> bool {
+        false
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, IoError>;
+
+    /// Lists the files under a directory, going only into the directories `enter` accepts.
+    /// Ignore files are honoured, and symbolic links followed, where the file system supports
+    /// them and the flags are set.
+    ///
+    /// Paths below the root that cannot be read are returned alongside the files rather than
+    /// ending the walk. Only a root that cannot be read is an error.
+    fn walk(
+        &self,
+        root: &Path,
+        respect_ignore_files: bool,
+        follow_symlinks: bool,
+        enter: EnterDir,
+    ) -> Result<Walked, IoError>;
+}
+
+/// The disk, through `std::fs`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFileSystem;
+
+/// A tree of files held in memory. Paths are used as given, apart from `.` components, and the
+/// tree has no ignore files or symbolic links.
+#[derive(Debug, Default)]
+pub struct InMemoryFileSystem {
+    tree: Mutex<Tree>,
+}
+
+#[derive(Debug, Default)]
+struct Tree {
+    files: BTreeMap<PathBuf, String>,
+    dirs: BTreeSet<PathBuf>,
+    denied: BTreeSet<PathBuf>,
+}
+
+impl FileSystem for RealFileSystem {
+    fn read_to_string(&self, path: &Path) -> Result<String, IoError> {
+        fs::read_to_string(path)
+    }
+
+    /// Writes a file beside the target and renames it into place, so the target is never left
+    /// half written. An existing target's permissions are kept. The file written first is named
+    /// after the process and a count, so runs and threads writing at once do not collide.
+    fn write(&self, path: &Path, contents: &str) -> Result<(), IoError> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let count = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+        let temp = path.with_file_name(format!(".{}.{}.{}.wsinit", name, process::id(), count));
+
+        let written = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+            .and_then(|mut file| {
+                file.write_all(contents.as_bytes())?;
+                if let Ok(metadata) = fs::metadata(path) {
+                    file.set_permissions(metadata.permissions())?;
+                }
+                file.sync_all()
+            })
+            .and_then(|()| fs::rename(&temp, path));
+
+        if written.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        written
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), IoError> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), IoError> {
+        fs::remove_dir_all(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), IoError> {
+        fs::rename(from, to)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<(), IoError> {
+        fs::copy(from, to).map(|_| ())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), IoError> {
+        fs::create_dir_all(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, IoError> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+
+    /// Symlinks are not followed.
+    fn file_size(&self, path: &Path) -> Result<u64, IoError> {
+        fs::symlink_metadata(path).map(|metadata| metadata.len())
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, IoError> {
+        fs::canonicalize(path)
+    }
+
+    fn walk(
+        &self,
+        root: &Path,
+        respect_ignore_files: bool,
+        follow_symlinks: bool,
+        enter: EnterDir,
+    ) -> Result<Walked, IoError> {
+        // the walk would report an unreadable root like any other path
+        fs::read_dir(root)?;
+
+        let mut files = vec![];
+        let mut errors = vec![];
+
+        let walker = WalkBuilder::new(root)
+            .standard_filters(respect_ignore_files)
+            .follow_links(follow_symlinks)
+            .hidden(false)
+            .filter_entry(move |entry| {
+                let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                !is_dir || enter(entry.path())
+            })
+            .build();
+
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    errors.extend(walk_error(root, err));
+                    continue;
+                }
+            };
+
+            if entry.file_type().is_some_and(|t| t.is_file()) {
+                files.push(entry.into_path());
+            }
+        }
+
+        Ok((files, errors))
+    }
+}
+
+impl InMemoryFileSystem {
+    pub fn new() -> InMemoryFileSystem {
+        InMemoryFileSystem::default()
+    }
+
+    /// Adds a file, and the directories above it, replacing any file already at the path.
+    pub fn add_file(&self, path: impl AsRef<Path>, contents: &str) {
+        let path = normalise(path.as_ref());
+        let mut tree = self.tree();
+
+        if let Some(parent) = path.parent() {
+            tree.add_dirs(parent);
+        }
+        tree.files.insert(path, contents.to_string());
+    }
+
+    /// Makes reading the file or directory at the path fail with a permission error, as it would
+    /// for a path on disk the user cannot read.
+    pub fn deny(&self, path: impl AsRef<Path>) {
+        self.tree().denied.insert(normalise(path.as_ref()));
+    }
+
+    fn tree(&self) -> MutexGuard<'_, Tree> {
+        self.tree
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl FileSystem for InMemoryFileSystem {
+    fn read_to_string(&self, path: &Path) -> Result<String, IoError> {
+        let path = normalise(path);
+        let tree = self.tree();
+
+        if tree.denied.contains(&path) {
+            return Err(permission_denied());
+        }
+
+        tree.files
+            .get(&path)
+            .cloned()
+            .ok_or_else(|| not_found(&path))
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> Result<(), IoError> {
+        let path = normalise(path);
+        let mut tree = self.tree();
+
+        if tree.dirs.contains(&path) {
+            return Err(IoError::new(ErrorKind::InvalidInput, "is a directory"));
+        }
+        match path.parent() {
+            Some(parent) if !tree.dirs.contains(parent) => return Err(not_found(parent)),
+            _ => {}
+        }
+
+        tree.files.insert(path, contents.to_string());
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), IoError> {
+        self.tree()
+            .files
+            .remove(&normalise(path))
+            .map(|_| ())
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), IoError> {
+        let path = normalise(path);
+        let mut tree = self.tree();
+
+        if !tree.dirs.contains(&path) {
+            return Err(not_found(&path));
+        }
+
+        tree.files.retain(|file, _| !file.starts_with(&path));
+        tree.dirs.retain(|dir| !dir.starts_with(&path));
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), IoError> {
+        let (from, to) = (normalise(from), normalise(to));
+        let mut tree = self.tree();
+
+        if !tree.files.contains_key(&from) && !tree.dirs.contains(&from) {
+            return Err(not_found(&from));
+        }
+        match to.parent() {
+            Some(parent) if !tree.dirs.contains(parent) => return Err(not_found(parent)),
+            _ => {}
+        }
+
+        let moved = |path: &PathBuf| match path.strip_prefix(&from) {
+            Ok(rest) if rest.as_os_str().is_empty() => Some(to.clone()),
+            Ok(rest) => Some(to.join(rest)),
+            Err(_) => None,
+        };
+
+        let files: Vec<(PathBuf, String)> = tree
+            .files
+            .iter()
+            .filter_map(|(path, contents)| moved(path).map(|path| (path, contents.clone())))
+            .collect();
+        let dirs: Vec<PathBuf> = tree.dirs.iter().filter_map(moved).collect();
+
+        tree.files.retain(|file, _| !file.starts_with(&from));
+        tree.dirs.retain(|dir| !dir.starts_with(&from));
+        tree.files.extend(files);
+        tree.dirs.extend(dirs);
+        Ok(())
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<(), IoError> {
+        let contents = self.read_to_string(from)?;
+        self.write(to, &contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), IoError> {
+        let path = normalise(path);
+        let mut tree = self.tree();
+
+        if tree.files.contains_key(&path) {
+            return Err(IoError::new(ErrorKind::AlreadyExists, "is a file"));
+        }
+
+        tree.add_dirs(&path);
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, IoError> {
+        let path = normalise(path);
+        let tree = self.tree();
+
+        if !tree.dirs.contains(&path) {
+            return Err(not_found(&path));
+        }
+        if tree.denied.contains(&path) {
+            return Err(permission_denied());
+        }
+
+        let child = |entry: &&PathBuf| entry.parent() == Some(&path);
+        let mut entries: Vec<PathBuf> = tree.dirs.iter().filter(child).cloned().collect();
+        entries.extend(tree.files.keys().filter(child).cloned());
+        Ok(entries)
+    }
+
+    fn file_size(&self, path: &Path) -> Result<u64, IoError> {
+        self.read_to_string(path)
+            .map(|contents| contents.len() as u64)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.tree().files.contains_key(&normalise(path))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.tree().dirs.contains(&normalise(path))
+    }
+
+    /// Resolves `..` components without any symlinks to follow.
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, IoError> {
+        let mut resolved = PathBuf::new();
+
+        for component in path.components() {
+            match component {
+                Component::ParentDir => {
+                    resolved.pop();
+                }
+                Component::CurDir => {}
+                component => resolved.push(component),
+            }
+        }
+
+        if self.exists(&resolved) {
+            Ok(resolved)
+        } else {
+            Err(not_found(path))
+        }
+    }
+
+    fn walk(
+        &self,
+        root: &Path,
+        _respect_ignore_files: bool,
+        _follow_symlinks: bool,
+        enter: EnterDir,
+    ) -> Result<Walked, IoError> {
+        let root = normalise(root);
+        let mut files = vec![];
+        let mut errors = vec![];
+
+        let mut pending = self.read_dir(&root)?;
+
+        while let Some(path) = pending.pop() {
+            if !self.is_dir(&path) {
+                files.push(path);
+                continue;
+            }
+            if !enter(&path) {
+                continue;
+            }
+
+            match self.read_dir(&path) {
+                Ok(entries) => pending.extend(entries),
+                Err(err) => errors.push((path, err)),
+            }
+        }
+
+        Ok((files, errors))
+    }
+}
+
+impl Tree {
+    fn add_dirs(&mut self, path: &Path) {
+        // a relative path's ancestors end with the empty path, which stands for `.`
+        for dir in path.ancestors() {
+            self.dirs.insert(dir.to_path_buf());
+        }
+    }
+}
+
+fn normalise(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+/// The path and io error behind a walk error. Problems with ignore files do not stop the walk, so
+/// are left out.
+fn walk_error(root: &Path, err: WalkError) -> Option<(PathBuf, IoError)> {
+    match err {
+        WalkError::WithPath { path, err } => walk_error(root, *err).map(|(_, err)| (path, err)),
+        WalkError::WithDepth { err, .. } => walk_error(root, *err),
+        WalkError::Loop { child, .. } => Some((
+            child,
+            IoError::new(ErrorKind::InvalidData, "symbolic link to an ancestor"),
+        )),
+        WalkError::Io(err) => Some((root.to_path_buf(), err)),
+        _ => None,
+    }
+}
+
+fn not_found(path: &Path) -> IoError {
+    IoError::new(ErrorKind::NotFound, format!("{} not found", path.display()))
+}
+
+fn permission_denied() -> IoError {
+    IoError::new(ErrorKind::PermissionDenied, "permission denied")
+}
@@ -1,10 +1,38 @@
+pub use crate::conflict::Conflict;
+pub use crate::discovery_warning::DiscoveryWarning;
+pub use crate::error::{Error, Operation};
+pub use crate::external_path_dependency::ExternalPathDependency;
+pub use crate::file_system::{EnterDir, FileSystem, InMemoryFileSystem, RealFileSystem, Walked};
+pub use crate::manifest_change::ManifestChange;
 pub use crate::options::FileExistsBehaviour;
+pub use crate::options::MissingMemberBehaviour;
+pub use crate::options::NestedWorkspaceBehaviour;
 pub use crate::options::Options;
+pub use crate::options::Resolver;
+pub use crate::options::SortOrder;
+pub use crate::options_builder::{OptionsBuilder, OptionsError};
+pub use crate::resolver_mismatch::ResolverMismatch;
+pub use crate::skipped_crate::{SkipReason, SkippedCrate};
+pub use crate::stale_target::StaleTarget;
+pub use crate::toml_changes::TomlChanges;
 pub use crate::toml_file::TomlFile;
-pub use crate::workspace::Error;
+pub use crate::update_report::{Action, ReportedError, UpdateReport};
 pub use crate::workspace::Workspace;
 
+mod conflict;
+mod discovery_warning;
+mod error;
+mod external_path_dependency;
+mod file_system;
+mod lockfile;
+mod manifest_change;
 mod options;
+mod options_builder;
+mod resolver_mismatch;
+mod skipped_crate;
+mod stale_target;
+mod toml_changes;
 mod toml_editor;
 mod toml_file;
+mod update_report;
 mod workspace;
@@ -0,0 +1,486 @@
+use std::collections::BTreeMap;
+
+use toml_edit::{Document, Table, TomlError};
+
+use crate::toml_editor;
+
+const HEADER: &str = "# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+";
+
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+    checksum: Option<String>,
+    dependencies: Vec<LockedDependency>,
+}
+
+/// The name, version and source of a dependency. Path packages have no source.
+type LockedDependency = (String, String, Option<String>);
+
+/// Merges lockfiles into one that pins the registry and git packages they pin. Where they pin
+/// semver compatible versions of a package from the same source differently, the highest wins.
+/// Path packages, such as the members themselves, are left for cargo to add back. Checksums from
+/// the `[metadata]` table of older lockfiles are kept with their packages.
+pub fn merge_lockfiles(lockfiles: &[String]) -> Result<String, TomlError> {
+    let mut format_version = None;
+    let mut packages = vec![];
+
+    for contents in lockfiles {
+        let doc = contents.parse::<Document>()?;
+
+        if let Some(version) = doc.as_table().get("version").and_then(|v| v.as_integer()) {
+            format_version = format_version.max(Some(version));
+        }
+        packages.extend(read_packages(doc.as_table()));
+    }
+
+    // the highest version of each semver compatible release of a package from a source
+    let mut chosen: BTreeMap<(String, Option<String>, String), String> = BTreeMap::new();
+    for package in &packages {
+        let key = (
+            package.name.clone(),
+            package.source.clone(),
+            compatible(&package.version),
+        );
+        let highest = chosen.entry(key).or_insert_with(|| package.version.clone());
+        if version_order(&package.version) > version_order(highest) {
+            *highest = package.version.clone();
+        }
+    }
+
+    let mut merged: BTreeMap<(String, VersionOrder, Option<String>), LockedPackage> =
+        BTreeMap::new();
+    for mut package in packages {
+        let key = (
+            package.name.clone(),
+            package.source.clone(),
+            compatible(&package.version),
+        );
+        if chosen[&key] != package.version {
+            continue;
+        }
+
+        for (name, version, source) in &mut package.dependencies {
+            let key = (name.clone(), source.clone(), compatible(version));
+            if let Some(highest) = chosen.get(&key) {
+                *version = highest.clone();
+            }
+        }
+
+        let key = (
+            package.name.clone(),
+            version_order(&package.version),
+            package.source.clone(),
+        );
+        match merged.get_mut(&key) {
+            // the same package resolved with other features may depend on more
+            Some(existing) => existing.dependencies.extend(package.dependencies),
+            None => {
+                merged.insert(key, package);
+            }
+        }
+    }
+
+    let mut versions: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut sources: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for package in merged.values() {
+        *versions.entry(&package.name).or_default() += 1;
+        *sources
+            .entry((&package.name, &package.version))
+            .or_default() += 1;
+    }
+
+    let mut lockfile = HEADER.to_string();
+    if let Some(version) = format_version {
+        lockfile.push_str(&format!("version = {}\n", version));
+    }
+
+    for package in merged.values() {
+        lockfile.push_str("\n[[package]]\n");
+        lockfile.push_str(&format!("name = {}\n", quote(&package.name)));
+        lockfile.push_str(&format!("version = {}\n", quote(&package.version)));
+        if let Some(source) = &package.source {
+            lockfile.push_str(&format!("source = {}\n", quote(source)));
+        }
+        if let Some(checksum) = &package.checksum {
+            lockfile.push_str(&format!("checksum = {}\n", quote(checksum)));
+        }
+
+        // a name alone is only enough while one version of the package is locked, and a name
+        // and version while that version comes from one source
+        let mut dependencies: Vec<String> = package
+            .dependencies
+            .iter()
+            .map(|(name, version, source)| {
+                let same_version = sources.get(&(name.as_str(), version.as_str()));
+                match (source, same_version, versions.get(name.as_str())) {
+                    (Some(source), Some(count), _) if *count > 1 => {
+                        format!("{} {} ({})", name, version, source)
+                    }
+                    (_, _, Some(count)) if *count > 1 => format!("{} {}", name, version),
+                    _ => name.clone(),
+                }
+            })
+            .collect();
+        dependencies.sort();
+        dependencies.dedup();
+
+        if !dependencies.is_empty() {
+            lockfile.push_str("dependencies = [\n");
+            for dependency in dependencies {
+                lockfile.push_str(&format!(" {},\n", quote(&dependency)));
+            }
+            lockfile.push_str("]\n");
+        }
+    }
+
+    Ok(lockfile)
+}
+
+fn read_packages(lockfile: &Table) -> Vec<LockedPackage> {
+    let tables: Vec<&Table> = match lockfile.get("package").and_then(|p| p.as_array_of_tables()) {
+        Some(tables) => tables.iter().collect(),
+        None => return vec![],
+    };
+
+    let field =
+        |table: &Table, key: &str| table.get(key).and_then(|v| v.as_str()).map(String::from);
+
+    // dependencies given by name alone are the only version of that name in the file, and by
+    // name and version the only source of that version
+    let mut versions: BTreeMap<String, (String, Option<String>)> = BTreeMap::new();
+    let mut sources: BTreeMap<(String, String), Option<String>> = BTreeMap::new();
+    for table in &tables {
+        if let (Some(name), Some(version)) = (field(table, "name"), field(table, "version")) {
+            let source = field(table, "source");
+            versions.insert(name.clone(), (version.clone(), source.clone()));
+            sources.insert((name, version), source);
+        }
+    }
+
+    // version 1 lockfiles keep checksums apart, keyed by "checksum name version (source)"
+    let checksums: BTreeMap<String, String> = lockfile
+        .get("metadata")
+        .and_then(|m| m.as_table())
+        .map(|metadata| {
+            metadata
+                .iter()
+                .filter_map(|(key, value)| {
+                    let package = key.strip_prefix("checksum ")?;
+                    let checksum = value.as_str().filter(|c| *c != "<none>")?;
+                    Some((package.to_string(), checksum.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    tables
+        .iter()
+        .filter_map(|table| {
+            let dependencies = table
+                .get("dependencies")
+                .and_then(|d| d.as_array())
+                .map(|dependencies| {
+                    dependencies
+                        .iter()
+                        .filter_map(|d| d.as_str())
+                        .filter_map(|d| {
+                            let mut parts = d.splitn(3, ' ');
+                            let name = parts.next()?.to_string();
+                            let (version, source) = match (parts.next(), parts.next()) {
+                                (Some(version), Some(source)) => {
+                                    let source = source.trim_start_matches('(');
+                                    let source = source.trim_end_matches(')');
+                                    (version.to_string(), Some(source.to_string()))
+                                }
+                                (Some(version), None) => {
+                                    let key = (name.clone(), version.to_string());
+                                    (version.to_string(), sources.get(&key)?.clone())
+                                }
+                                _ => versions.get(&name)?.clone(),
+                            };
+                            Some((name, version, source))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let name = field(table, "name")?;
+            let version = field(table, "version")?;
+            let source = field(table, "source")?;
+            let checksum = field(table, "checksum").or_else(|| {
+                let key = format!("{} {} ({})", name, version, source);
+                checksums.get(&key).cloned()
+            });
+
+            Some(LockedPackage {
+                name,
+                version,
+                source: Some(source),
+                checksum,
+                dependencies,
+            })
+        })
+        .collect()
+}
+
+type VersionOrder = (Vec<u64>, bool, String);
+
+/// Orders versions by their numbers, then releases after pre-releases.
+fn version_order(version: &str) -> VersionOrder {
+    let version = version.split('+').next().unwrap_or(version);
+    let (release, pre) = match version.find('-') {
+        Some(index) => (&version[..index], &version[index + 1..]),
+        None => (version, ""),
+    };
+
+    (
+        release.split('.').map(|n| n.parse().unwrap_or(0)).collect(),
+        pre.is_empty(),
+        pre.to_string(),
+    )
+}
+
+/// The part of a version that semver compatible versions share, e.g. `1` for `1.2.3` and `0.2`
+/// for `0.2.3`.
+fn compatible(version: &str) -> String {
+    let (numbers, _, _) = version_order(version);
+
+    match numbers.as_slice() {
+        [0, 0, patch, ..] => format!("0.0.{}", patch),
+        [0, minor, ..] => format!("0.{}", minor),
+        [major, ..] => major.to_string(),
+        [] => String::new(),
+    }
+}
+
+fn quote(text: &str) -> String {
+    toml_editor::string_value(text).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highest_compatible_version_wins() {
+        let a = r#"version = 3
+
+[[package]]
+name = "a"
+version = "0.1.0"
+dependencies = [
+ "log",
+]
+
+[[package]]
+name = "log"
+version = "0.4.8"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "aaa"
+"#;
+        let b = r#"version = 3
+
+[[package]]
+name = "b"
+version = "0.1.0"
+dependencies = [
+ "log 0.4.11",
+ "log 0.3.9",
+]
+
+[[package]]
+name = "log"
+version = "0.3.9"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "ccc"
+dependencies = [
+ "log 0.4.11",
+]
+
+[[package]]
+name = "log"
+version = "0.4.11"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "bbb"
+"#;
+
+        let merged = merge_lockfiles(&[a.to_string(), b.to_string()]).unwrap();
+
+        assert_eq!(
+            format!(
+                "{}{}",
+                HEADER,
+                r#"version = 3
+
+[[package]]
+name = "log"
+version = "0.3.9"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "ccc"
+dependencies = [
+ "log 0.4.11",
+]
+
+[[package]]
+name = "log"
+version = "0.4.11"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "bbb"
+"#
+            ),
+            merged
+        );
+    }
+
+    #[test]
+    fn keep_a_git_source_beside_the_registry() {
+        let a = r#"version = 3
+
+[[package]]
+name = "a"
+version = "0.1.0"
+dependencies = [
+ "log",
+]
+
+[[package]]
+name = "log"
+version = "0.4.8"
+source = "git+https://example.com/log#abc"
+"#;
+        let b = r#"version = 3
+
+[[package]]
+name = "b"
+version = "0.1.0"
+dependencies = [
+ "log",
+]
+
+[[package]]
+name = "log"
+version = "0.4.8"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "aaa"
+
+[[package]]
+name = "uses-log"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "bbb"
+dependencies = [
+ "log",
+]
+"#;
+
+        let merged = merge_lockfiles(&[a.to_string(), b.to_string()]).unwrap();
+
+        assert_eq!(
+            format!(
+                "{}{}",
+                HEADER,
+                r#"version = 3
+
+[[package]]
+name = "log"
+version = "0.4.8"
+source = "git+https://example.com/log#abc"
+
+[[package]]
+name = "log"
+version = "0.4.8"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "aaa"
+
+[[package]]
+name = "uses-log"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "bbb"
+dependencies = [
+ "log 0.4.8 (registry+https://github.com/rust-lang/crates.io-index)",
+]
+"#
+            ),
+            merged
+        );
+        assert_eq!(
+            vec![(
+                "log".to_string(),
+                "0.4.8".to_string(),
+                Some("registry+https://github.com/rust-lang/crates.io-index".to_string())
+            )],
+            read_packages(merged.parse::<Document>().unwrap().as_table())[2].dependencies
+        );
+    }
+
+    #[test]
+    fn keep_versions_that_are_not_semver_compatible() {
+        let a = r#"version = 3
+
+[[package]]
+name = "rand"
+version = "0.7.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "aaa"
+
+[[package]]
+name = "uses-rand"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "bbb"
+dependencies = [
+ "rand",
+]
+"#;
+        let b = r#"version = 3
+
+[[package]]
+name = "rand"
+version = "0.8.5"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "ccc"
+"#;
+
+        let merged = merge_lockfiles(&[a.to_string(), b.to_string()]).unwrap();
+
+        assert!(merged.contains("version = \"0.7.3\""));
+        assert!(merged.contains("version = \"0.8.5\""));
+        assert!(merged.contains("dependencies = [\n \"rand 0.7.3\",\n]"));
+    }
+
+    #[test]
+    fn keep_version_1_metadata_checksums() {
+        let a = r#"[[package]]
+name = "log"
+version = "0.4.8"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[metadata]
+"checksum log 0.4.8 (registry+https://github.com/rust-lang/crates.io-index)" = "aaa"
+"#;
+
+        let merged = merge_lockfiles(&[a.to_string()]).unwrap();
+
+        assert!(merged.contains("version = \"0.4.8\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\nchecksum = \"aaa\"\n"));
+    }
+
+    #[test]
+    fn quote_as_basic_strings() {
+        assert_eq!("\"'x'\"", quote("'x'"));
+        assert_eq!("\"a\\\\nb\"", quote("a\\nb"));
+        assert_eq!("\"a'''\\\"\\\"\\\"b\"", quote("a'''\"\"\"b"));
+    }
+
+    #[test]
+    fn order_versions() {
+        assert!(version_order("1.10.0") > version_order("1.9.0"));
+        assert!(version_order("1.0.0") > version_order("1.0.0-alpha"));
+        assert_eq!("0.4", compatible("0.4.11"));
+        assert_eq!("1", compatible("1.2.3"));
+        assert_eq!("0.0.3", compatible("0.0.3"));
+    }
+}
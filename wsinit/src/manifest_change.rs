@@ -0,0 +1,45 @@
+use similar::TextDiff;
+
+use crate::toml_file::TomlFile;
+
+/// A change to a member's manifest, or to a lockfile, that is made alongside the workspace toml
+/// file.
+#[derive(Clone, Debug)]
+pub struct ManifestChange {
+    toml: TomlFile,
+    current: String,
+    proposed: Option<String>,
+}
+
+impl ManifestChange {
+    pub(crate) fn new(toml: TomlFile, current: String, proposed: Option<String>) -> ManifestChange {
+        ManifestChange {
+            toml,
+            current,
+            proposed,
+        }
+    }
+
+    pub fn toml(&self) -> &TomlFile {
+        &self.toml
+    }
+
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// The contents that would be written to the file, or `None` if it would be deleted.
+    pub fn proposed(&self) -> Option<&str> {
+        self.proposed.as_deref()
+    }
+
+    /// A unified diff from the current file to the proposed contents.
+    pub fn diff(&self) -> String {
+        let name = self.toml.to_string();
+
+        TextDiff::from_lines(self.current(), self.proposed().unwrap_or(""))
+            .unified_diff()
+            .header(&name, &name)
+            .to_string()
+    }
+}
@@ -1,8 +1,32 @@
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::file_system::{FileSystem, RealFileSystem};
+use crate::options_builder::OptionsBuilder;
 
 pub struct Options {
     pub(crate) path: PathBuf,
     pub(crate) existing_file_behaviour: FileExistsBehaviour,
+    pub(crate) respect_ignore_files: bool,
+    pub(crate) follow_symlinks: bool,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) sort_order: SortOrder,
+    pub(crate) dry_run: bool,
+    pub(crate) strict: bool,
+    pub(crate) missing_member_behaviour: MissingMemberBehaviour,
+    pub(crate) excludes: Vec<String>,
+    pub(crate) collapse_globs: bool,
+    pub(crate) nested_workspace_behaviour: NestedWorkspaceBehaviour,
+    pub(crate) move_root_package: Option<String>,
+    pub(crate) hoist_dependencies: bool,
+    pub(crate) inherit_package_fields: bool,
+    pub(crate) workspace_path_dependencies: bool,
+    pub(crate) resolver: Option<Resolver>,
+    pub(crate) consolidate_profiles: bool,
+    pub(crate) consolidate_patches: bool,
+    pub(crate) merge_lockfiles: bool,
+    pub(crate) backup: bool,
+    pub(crate) file_system: Arc<dyn FileSystem>,
 }
 
 #[derive(PartialEq)]
@@ -17,23 +41,122 @@ pub enum FileExistsBehaviour {
     Overwrite,
 }
 
+#[derive(PartialEq)]
+pub enum MissingMemberBehaviour {
+    /// When updating, members whose directory no longer holds a crate are left in place.
+    Keep,
+
+    /// When updating, members whose directory no longer holds a crate are commented out.
+    CommentOut,
+
+    /// When updating, members whose directory no longer holds a crate are removed.
+    Remove,
+}
+
+#[derive(PartialEq)]
+pub enum NestedWorkspaceBehaviour {
+    /// A crate that is the root of its own workspace is skipped and reported.
+    Skip,
+
+    /// A nested workspace's members become members of this workspace, and its `[workspace]`
+    /// table is removed. A nested virtual manifest is deleted. A nested workspace with settings
+    /// this would lose, like `[workspace.dependencies]` or `[profile]`, is excluded and reported
+    /// instead.
+    Absorb,
+
+    /// A nested workspace is added to this workspace's excludes.
+    Exclude,
+}
+
+/// Where new members and excludes go in an existing toml file. A new file is always sorted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortOrder {
+    /// New entries are inserted in sorted position.
+    Sorted,
+
+    /// New entries are added after the existing ones, leaving a hand ordered list alone.
+    Append,
+}
+
+/// The `[workspace]` resolver version.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Resolver {
+    V1,
+    V2,
+    V3,
+}
+
 impl Options {
+    /// Start building options for the specified path (not including the Cargo.toml file itself).
+    pub fn builder(path: impl AsRef<Path>) -> OptionsBuilder {
+        OptionsBuilder::new(path)
+    }
+
     /// Create a new options struct for the specified path (not including the Cargo.toml file itself)
-    /// and the specified existing file behaviour.
+    /// and the specified existing file behaviour. Every other setting keeps its default; use
+    /// [`Options::builder`] to change them.
     pub fn new(path: &str, overwrite: FileExistsBehaviour) -> Options {
         Options {
             path: Path::new(path).into(),
             existing_file_behaviour: overwrite,
+            respect_ignore_files: true,
+            follow_symlinks: true,
+            max_depth: None,
+            sort_order: SortOrder::Sorted,
+            dry_run: false,
+            strict: false,
+            missing_member_behaviour: MissingMemberBehaviour::Keep,
+            excludes: vec![],
+            collapse_globs: false,
+            nested_workspace_behaviour: NestedWorkspaceBehaviour::Skip,
+            move_root_package: None,
+            hoist_dependencies: false,
+            inherit_package_fields: false,
+            workspace_path_dependencies: false,
+            resolver: None,
+            consolidate_profiles: false,
+            consolidate_patches: false,
+            merge_lockfiles: false,
+            backup: false,
+            file_system: Arc::new(RealFileSystem),
         }
     }
 }
 
 impl FileExistsBehaviour {
-    pub(crate) fn create_new(&self) -> bool {
+    pub(crate) fn replaces(&self) -> bool {
+        match self {
+            FileExistsBehaviour::Halt => false,
+            FileExistsBehaviour::Update => true,
+            FileExistsBehaviour::Overwrite => true,
+        }
+    }
+}
+
+impl Resolver {
+    /// The resolver an edition defaults to.
+    pub fn for_edition(edition: &str) -> Resolver {
+        match edition {
+            "2015" | "2018" => Resolver::V1,
+            "2021" => Resolver::V2,
+            _ => Resolver::V3,
+        }
+    }
+
+    pub(crate) fn parse(resolver: &str) -> Option<Resolver> {
+        match resolver {
+            "1" => Some(Resolver::V1),
+            "2" => Some(Resolver::V2),
+            "3" => Some(Resolver::V3),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
         match self {
-            FileExistsBehaviour::Halt => true,
-            FileExistsBehaviour::Update => false,
-            FileExistsBehaviour::Overwrite => false,
+            Resolver::V1 => "1",
+            Resolver::V2 => "2",
+            Resolver::V3 => "3",
         }
     }
 }
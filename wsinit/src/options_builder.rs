@@ -0,0 +1,244 @@
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::Path;
+use std::sync::Arc;
+
+use globset::GlobBuilder;
+
+use crate::file_system::FileSystem;
+use crate::options::{FileExistsBehaviour, MissingMemberBehaviour, NestedWorkspaceBehaviour};
+use crate::options::{Options, Resolver, SortOrder};
+
+/// Builds [`Options`], checking at [`build`](OptionsBuilder::build) that the settings make sense
+/// together. Settings not given keep their defaults.
+pub struct OptionsBuilder {
+    options: Options,
+}
+
+/// Why [`OptionsBuilder::build`] refused the settings.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OptionsError {
+    /// The workspace path is empty.
+    EmptyPath,
+
+    /// A max depth of zero leaves nothing to search.
+    ZeroMaxDepth,
+
+    /// An exclude is not a valid glob.
+    InvalidExclude(String),
+
+    /// Moving the root package needs [`FileExistsBehaviour::Update`], as the package is read from
+    /// the existing toml file.
+    MoveRootPackageWithoutUpdate,
+}
+
+impl OptionsBuilder {
+    /// Options for the specified path (not including the Cargo.toml file itself) that halt if the
+    /// toml file already exists.
+    pub fn new(path: impl AsRef<Path>) -> OptionsBuilder {
+        let mut options = Options::new("", FileExistsBehaviour::Halt);
+        options.path = path.as_ref().to_path_buf();
+
+        OptionsBuilder { options }
+    }
+
+    /// What happens when the toml file already exists. Defaults to halting.
+    pub fn existing_file_behaviour(mut self, behaviour: FileExistsBehaviour) -> OptionsBuilder {
+        self.options.existing_file_behaviour = behaviour;
+        self
+    }
+
+    /// Exclude a path or glob, relative to the workspace root, from the search for crates. It is
+    /// added to the `[workspace]` exclude array alongside any paths already listed there.
+    pub fn exclude(mut self, path: impl AsRef<str>) -> OptionsBuilder {
+        self.options.excludes.push(path.as_ref().to_string());
+        self
+    }
+
+    /// How many directories below the root to look for crates, so 1 only finds crates in the
+    /// root's own subdirectories. Unlimited by default.
+    pub fn max_depth(mut self, depth: usize) -> OptionsBuilder {
+        self.options.max_depth = Some(depth);
+        self
+    }
+
+    /// Whether the search for crates follows symbolic links to directories. Defaults to true.
+    pub fn follow_symlinks(mut self, follow: bool) -> OptionsBuilder {
+        self.options.follow_symlinks = follow;
+        self
+    }
+
+    /// Whether `.gitignore`, `.ignore`, `.git/info/exclude` and global git excludes are honoured
+    /// when searching for crates. Ignored directories are not descended into. Defaults to true.
+    pub fn respect_ignore_files(mut self, respect: bool) -> OptionsBuilder {
+        self.options.respect_ignore_files = respect;
+        self
+    }
+
+    /// Where new members and excludes go in an existing toml file. Defaults to sorted.
+    pub fn sort_order(mut self, order: SortOrder) -> OptionsBuilder {
+        self.options.sort_order = order;
+        self
+    }
+
+    /// Whether members that are all of a directory's entries are written as one `<dir>/*` glob.
+    /// Defaults to false.
+    pub fn collapse_globs(mut self, collapse: bool) -> OptionsBuilder {
+        self.options.collapse_globs = collapse;
+        self
+    }
+
+    /// Work out the changes an update would make, but write, move and delete nothing. Defaults to
+    /// false.
+    pub fn dry_run(mut self, dry_run: bool) -> OptionsBuilder {
+        self.options.dry_run = dry_run;
+        self
+    }
+
+    /// Fail with [`Error::Unsearched`](crate::Error::Unsearched) when paths below the root cannot
+    /// be searched for crates, rather than reporting them as discovery warnings. Defaults to false.
+    pub fn strict(mut self, strict: bool) -> OptionsBuilder {
+        self.options.strict = strict;
+        self
+    }
+
+    /// What an update does with existing members that no longer exist. Defaults to keeping them.
+    pub fn missing_member_behaviour(mut self, behaviour: MissingMemberBehaviour) -> OptionsBuilder {
+        self.options.missing_member_behaviour = behaviour;
+        self
+    }
+
+    /// What happens to crates that are the root of a workspace of their own. Defaults to skipping
+    /// them.
+    pub fn nested_workspace_behaviour(
+        mut self,
+        behaviour: NestedWorkspaceBehaviour,
+    ) -> OptionsBuilder {
+        self.options.nested_workspace_behaviour = behaviour;
+        self
+    }
+
+    /// Move the package in the root toml file, and its sources, into a directory relative to the
+    /// workspace root, leaving a virtual manifest behind. Only applies when updating.
+    pub fn move_root_package(mut self, dir: &str) -> OptionsBuilder {
+        self.options.move_root_package = Some(
+            dir.trim_start_matches("./")
+                .trim_end_matches('/')
+                .to_string(),
+        );
+        self
+    }
+
+    /// Move dependencies that two or more members require in the same way into
+    /// `[workspace.dependencies]`, and have the members inherit them. Defaults to false.
+    pub fn hoist_dependencies(mut self, hoist: bool) -> OptionsBuilder {
+        self.options.hoist_dependencies = hoist;
+        self
+    }
+
+    /// Move path dependencies on other members into `[workspace.dependencies]`, with a path
+    /// relative to the workspace root, and have the members inherit them. Defaults to false.
+    pub fn workspace_path_dependencies(mut self, workspace: bool) -> OptionsBuilder {
+        self.options.workspace_path_dependencies = workspace;
+        self
+    }
+
+    /// Move `[package]` fields such as `edition` and `license` that every member declares the same
+    /// into `[workspace.package]`, and have the members inherit them. Defaults to false.
+    pub fn inherit_package_fields(mut self, inherit: bool) -> OptionsBuilder {
+        self.options.inherit_package_fields = inherit;
+        self
+    }
+
+    /// Move `[profile]` settings from the members, where cargo ignores them, into the toml file.
+    /// Settings the members disagree on are left in place. Defaults to false.
+    pub fn consolidate_profiles(mut self, consolidate: bool) -> OptionsBuilder {
+        self.options.consolidate_profiles = consolidate;
+        self
+    }
+
+    /// Move `[patch]` and `[replace]` tables from the members, where cargo ignores them, into the
+    /// toml file, with paths relative to the workspace root. Defaults to false.
+    pub fn consolidate_patches(mut self, consolidate: bool) -> OptionsBuilder {
+        self.options.consolidate_patches = consolidate;
+        self
+    }
+
+    /// Merge the members' `Cargo.lock` files into the workspace `Cargo.lock`, keeping the highest
+    /// of the versions they pin, and delete them. Defaults to false.
+    pub fn merge_lockfiles(mut self, merge: bool) -> OptionsBuilder {
+        self.options.merge_lockfiles = merge;
+        self
+    }
+
+    /// The resolver to write to the toml file. By default a new file gets the resolver the
+    /// members' editions call for, and an existing file keeps its resolver.
+    pub fn resolver(mut self, resolver: Resolver) -> OptionsBuilder {
+        self.options.resolver = Some(resolver);
+        self
+    }
+
+    /// Copy an existing toml file to `Cargo.toml.bak` before replacing it, for
+    /// [`undo`](crate::Workspace::undo). Defaults to false.
+    pub fn backup(mut self, backup: bool) -> OptionsBuilder {
+        self.options.backup = backup;
+        self
+    }
+
+    /// The file system to search and update, e.g. an
+    /// [`InMemoryFileSystem`](crate::InMemoryFileSystem). Defaults to the disk.
+    pub fn file_system(mut self, file_system: Arc<dyn FileSystem>) -> OptionsBuilder {
+        self.options.file_system = file_system;
+        self
+    }
+
+    pub fn build(self) -> Result<Options, OptionsError> {
+        let options = self.options;
+
+        if options.path.as_os_str().is_empty() {
+            return Err(OptionsError::EmptyPath);
+        }
+
+        if options.max_depth == Some(0) {
+            return Err(OptionsError::ZeroMaxDepth);
+        }
+
+        // the same glob syntax the search uses
+        if let Some(exclude) = options.excludes.iter().find(|exclude| {
+            GlobBuilder::new(exclude.trim_start_matches("./").trim_end_matches('/'))
+                .literal_separator(true)
+                .build()
+                .is_err()
+        }) {
+            return Err(OptionsError::InvalidExclude(exclude.clone()));
+        }
+
+        if options.move_root_package.is_some()
+            && options.existing_file_behaviour != FileExistsBehaviour::Update
+        {
+            return Err(OptionsError::MoveRootPackageWithoutUpdate);
+        }
+
+        Ok(options)
+    }
+}
+
+impl Display for OptionsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            OptionsError::EmptyPath => write!(f, "the workspace path is empty"),
+            OptionsError::ZeroMaxDepth => write!(f, "a max depth of 0 finds no crates"),
+            OptionsError::InvalidExclude(exclude) => {
+                write!(f, "the exclude {} is not a valid glob", exclude)
+            }
+            OptionsError::MoveRootPackageWithoutUpdate => {
+                write!(
+                    f,
+                    "moving the root package needs the existing file to be updated"
+                )
+            }
+        }
+    }
+}
+
+impl StdError for OptionsError {}
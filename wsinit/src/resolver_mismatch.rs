@@ -0,0 +1,37 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::options::Resolver;
+
+/// An existing workspace resolver that is older than the members' editions call for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolverMismatch {
+    current: Resolver,
+    expected: Resolver,
+}
+
+impl ResolverMismatch {
+    pub(crate) fn new(current: Resolver, expected: Resolver) -> ResolverMismatch {
+        ResolverMismatch { current, expected }
+    }
+
+    /// The resolver the toml file sets, or defaults to.
+    pub fn current(&self) -> Resolver {
+        self.current
+    }
+
+    /// The resolver the newest member edition defaults to.
+    pub fn expected(&self) -> Resolver {
+        self.expected
+    }
+}
+
+impl Display for ResolverMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "resolver \"{}\" is older than the resolver \"{}\" the member editions call for",
+            self.current.as_str(),
+            self.expected.as_str()
+        )
+    }
+}
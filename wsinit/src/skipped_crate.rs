@@ -0,0 +1,73 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::{Path, PathBuf};
+
+/// A directory holding a `Cargo.toml` that was not added as a workspace member.
+#[derive(Clone, Debug)]
+pub struct SkippedCrate {
+    path: PathBuf,
+    reason: SkipReason,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SkipReason {
+    /// The manifest could not be read.
+    Unreadable(String),
+
+    /// The manifest is empty.
+    Empty,
+
+    /// The manifest is not valid toml.
+    Unparsable(String),
+
+    /// The manifest has no `[package]` table.
+    NoPackage,
+
+    /// The manifest has a `[workspace]` table of its own.
+    NestedWorkspace,
+
+    /// The manifest is the root of a workspace with settings, named here, that absorbing it
+    /// would lose, so it was excluded instead.
+    NestedWorkspaceSettings(Vec<String>),
+
+    /// The directory's path is not valid UTF-8, so cannot be written to the toml file.
+    NonUtf8Path,
+}
+
+impl SkippedCrate {
+    pub(crate) fn new(path: PathBuf, reason: SkipReason) -> SkippedCrate {
+        SkippedCrate { path, reason }
+    }
+
+    /// The directory of the crate, relative to the workspace root.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn reason(&self) -> &SkipReason {
+        &self.reason
+    }
+}
+
+impl Display for SkippedCrate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}: {}", self.path.display(), self.reason)
+    }
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            SkipReason::Unreadable(err) => write!(f, "Cargo.toml could not be read ({})", err),
+            SkipReason::Empty => write!(f, "Cargo.toml is empty"),
+            SkipReason::Unparsable(err) => write!(f, "Cargo.toml could not be parsed ({})", err),
+            SkipReason::NoPackage => write!(f, "Cargo.toml has no [package] table"),
+            SkipReason::NestedWorkspace => write!(f, "Cargo.toml is a workspace root"),
+            SkipReason::NestedWorkspaceSettings(settings) => write!(
+                f,
+                "Cargo.toml is a workspace root, excluded as absorbing it would lose {}",
+                settings.join(", ")
+            ),
+            SkipReason::NonUtf8Path => write!(f, "the path is not valid UTF-8"),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::Error as IoError;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::file_system::FileSystem;
+
+/// A member's own `target` directory, which the workspace build no longer uses.
+#[derive(Clone, Debug)]
+pub struct StaleTarget {
+    path: PathBuf,
+    size: u64,
+    file_system: Arc<dyn FileSystem>,
+}
+
+impl StaleTarget {
+    pub(crate) fn new(path: PathBuf, file_system: Arc<dyn FileSystem>) -> StaleTarget {
+        let size = StaleTarget::dir_size(file_system.as_ref(), &path);
+
+        StaleTarget {
+            path,
+            size,
+            file_system,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The total size of the files in the directory, in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Deletes the directory and everything in it.
+    pub fn remove(&self) -> Result<(), IoError> {
+        self.file_system.remove_dir_all(&self.path)
+    }
+
+    /// Adds up file sizes without following symlinks. Anything unreadable counts as empty.
+    fn dir_size(file_system: &dyn FileSystem, dir: &Path) -> u64 {
+        let entries = match file_system.read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return 0,
+        };
+
+        entries
+            .iter()
+            .map(|entry| {
+                if file_system.is_dir(entry) && !file_system.is_symlink(entry) {
+                    StaleTarget::dir_size(file_system, entry)
+                } else {
+                    file_system.file_size(entry).unwrap_or(0)
+                }
+            })
+            .sum()
+    }
+}
+
+impl Display for StaleTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+        let mut size = self.size as f64;
+        let mut unit = 0;
+
+        while size >= 1024.0 && unit < units.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+
+        if unit == 0 {
+            write!(f, "{} ({} {})", self.path.display(), self.size, units[unit])
+        } else {
+            write!(f, "{} ({:.1} {})", self.path.display(), size, units[unit])
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use similar::TextDiff;
+
+use crate::conflict::Conflict;
+use crate::discovery_warning::DiscoveryWarning;
+use crate::external_path_dependency::ExternalPathDependency;
+use crate::manifest_change::ManifestChange;
+use crate::resolver_mismatch::ResolverMismatch;
+use crate::skipped_crate::SkippedCrate;
+use crate::toml_editor;
+use crate::toml_file::TomlFile;
+
+/// The contents proposed for a workspace toml file, alongside what is currently on disk.
+#[derive(Clone, Debug)]
+pub struct TomlChanges {
+    toml: TomlFile,
+    current: Option<String>,
+    proposed: String,
+    before: Vec<String>,
+    after: Vec<String>,
+    added: Vec<String>,
+    removed: Vec<String>,
+    skipped: Vec<SkippedCrate>,
+    discovery_warnings: Vec<DiscoveryWarning>,
+    manifest_changes: Vec<ManifestChange>,
+    conflicts: Vec<Conflict>,
+    external_path_dependencies: Vec<ExternalPathDependency>,
+    resolver_mismatch: Option<ResolverMismatch>,
+    written: bool,
+}
+
+impl TomlChanges {
+    pub(crate) fn new(
+        toml: TomlFile,
+        current: Option<String>,
+        proposed: String,
+        skipped: Vec<SkippedCrate>,
+    ) -> TomlChanges {
+        // a current file that does not parse has no members we can report on
+        let before = current
+            .as_ref()
+            .and_then(|c| toml_editor::read_members(c).ok())
+            .unwrap_or_default();
+        let after = toml_editor::read_members(&proposed).unwrap_or_default();
+
+        let added = after
+            .iter()
+            .filter(|m| !before.contains(m))
+            .cloned()
+            .collect();
+        let removed = before
+            .iter()
+            .filter(|m| !after.contains(m))
+            .cloned()
+            .collect();
+
+        TomlChanges {
+            toml,
+            current,
+            proposed,
+            before,
+            after,
+            added,
+            removed,
+            skipped,
+            discovery_warnings: vec![],
+            manifest_changes: vec![],
+            conflicts: vec![],
+            external_path_dependencies: vec![],
+            resolver_mismatch: None,
+            written: false,
+        }
+    }
+
+    pub(crate) fn with_discovery_warnings(
+        mut self,
+        warnings: Vec<DiscoveryWarning>,
+    ) -> TomlChanges {
+        self.discovery_warnings = warnings;
+        self
+    }
+
+    pub(crate) fn with_manifest_changes(mut self, changes: Vec<ManifestChange>) -> TomlChanges {
+        self.manifest_changes = changes;
+        self
+    }
+
+    pub(crate) fn with_conflicts(mut self, conflicts: Vec<Conflict>) -> TomlChanges {
+        self.conflicts = conflicts;
+        self
+    }
+
+    pub(crate) fn with_external_path_dependencies(
+        mut self,
+        dependencies: Vec<ExternalPathDependency>,
+    ) -> TomlChanges {
+        self.external_path_dependencies = dependencies;
+        self
+    }
+
+    pub(crate) fn with_resolver_mismatch(
+        mut self,
+        mismatch: Option<ResolverMismatch>,
+    ) -> TomlChanges {
+        self.resolver_mismatch = mismatch;
+        self
+    }
+
+    pub(crate) fn with_written(mut self, written: bool) -> TomlChanges {
+        self.written = written;
+        self
+    }
+
+    pub fn toml(&self) -> &TomlFile {
+        &self.toml
+    }
+
+    /// The contents of the file on disk, if it exists.
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// The contents that would be written to the file.
+    pub fn proposed(&self) -> &str {
+        &self.proposed
+    }
+
+    /// The members listed in the current file, in file order.
+    pub fn before(&self) -> &[String] {
+        &self.before
+    }
+
+    /// The members listed in the proposed file, in file order.
+    pub fn after(&self) -> &[String] {
+        &self.after
+    }
+
+    /// Members in the proposed file that are not in the current file.
+    pub fn added(&self) -> &[String] {
+        &self.added
+    }
+
+    /// Members in the current file that are not in the proposed file.
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    /// Directories with a `Cargo.toml` that were not added as members, and why.
+    pub fn skipped(&self) -> &[SkippedCrate] {
+        &self.skipped
+    }
+
+    /// Paths below the root that could not be searched, so may hide crates that are missing from
+    /// the members.
+    pub fn discovery_warnings(&self) -> &[DiscoveryWarning] {
+        &self.discovery_warnings
+    }
+
+    /// Changes to member manifests that are made alongside the workspace toml file.
+    pub fn manifest_changes(&self) -> &[ManifestChange] {
+        &self.manifest_changes
+    }
+
+    /// Settings that were left in the members because they disagree.
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// Path dependencies of members that point outside the workspace root.
+    pub fn external_path_dependencies(&self) -> &[ExternalPathDependency] {
+        &self.external_path_dependencies
+    }
+
+    /// Set when an existing resolver is older than the members' editions call for.
+    pub fn resolver_mismatch(&self) -> Option<&ResolverMismatch> {
+        self.resolver_mismatch.as_ref()
+    }
+
+    /// True if an update wrote the toml file. False for a check, and when the file on disk already
+    /// had the proposed contents.
+    pub fn written(&self) -> bool {
+        self.written
+    }
+
+    /// True if the file on disk is missing or differs from the proposed contents, or a member
+    /// manifest would change.
+    pub fn is_stale(&self) -> bool {
+        self.current() != Some(self.proposed()) || !self.manifest_changes.is_empty()
+    }
+
+    /// A unified diff from the current files to the proposed contents.
+    pub fn diff(&self) -> String {
+        let name = self.toml.to_string();
+
+        let mut diff = TextDiff::from_lines(self.current().unwrap_or(""), self.proposed())
+            .unified_diff()
+            .header(&name, &name)
+            .to_string();
+
+        for change in &self.manifest_changes {
+            diff.push_str(&change.diff());
+        }
+
+        diff
+    }
+}
@@ -1,5 +1,9 @@
+use std::path::Path;
+
 use toml_edit::*;
 
+use crate::options::{MissingMemberBehaviour, SortOrder};
+
 const EMPTY_FILE_TEMPLATE: &str = r#"[workspace]
 
 members = [
@@ -12,9 +16,54 @@ const EMPTY_MEMBERS_COMMENT_BLOCK: &str = r#"commented_array = [
 ]
 "#;
 
+const MEMBER_INDENT: &str = "\n    ";
+
+/// Top level tables that belong to the workspace root rather than to a package.
+const WORKSPACE_ROOT_TABLES: [&str; 4] = ["workspace", "patch", "replace", "profile"];
+
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Keys a member may set on a dependency it inherits from the workspace.
+const MEMBER_DEPENDENCY_KEYS: [&str; 2] = ["features", "optional"];
+
+/// `[package]` fields that are the same across a workspace often enough to inherit.
+pub const INHERITABLE_PACKAGE_FIELDS: [&str; 5] = [
+    "edition",
+    "license",
+    "authors",
+    "repository",
+    "rust-version",
+];
+
+const TARGET_TABLES: [&str; 4] = ["bin", "example", "test", "bench"];
+
+/// What a manifest found while searching for crates declares.
+pub enum ManifestKind {
+    /// A `[package]` table and no `[workspace]` table.
+    Package,
+
+    /// A `[workspace]` table, with or without a package.
+    Workspace,
+
+    /// Neither table.
+    Other,
+}
+
+/// How an existing array entry relates to what was found on disk.
+pub enum ExistingEntry {
+    /// The entry is still valid and is kept.
+    Found,
+
+    /// The entry no longer exists and is handled by the [`MissingMemberBehaviour`].
+    Missing,
+
+    /// The entry is matched by one of the new entries and is removed.
+    Covered,
+}
+
 pub fn toml_update<T>(contents: &str, sub_projects: &[T]) -> Result<String, TomlError>
 where
-    T: AsRef<str> + Into<Value> + Clone,
+    T: AsRef<str>,
 {
     let contents = if contents.is_empty() {
         EMPTY_FILE_TEMPLATE
@@ -24,107 +73,1644 @@ where
 
     let mut doc = contents.parse::<Document>()?;
 
-    let mut array = Array::default();
+    let mut array = new_array(sub_projects);
+
+    if sub_projects.is_empty() {
+        array = commented_array(EMPTY_MEMBERS_COMMENT_BLOCK);
+    }
+
+    if doc["workspace"].is_none() {
+        doc["workspace"] = table();
+    }
+    doc["workspace"]["members"] = value(array);
+
+    Ok(doc.to_string())
+}
+
+/// Merges sub projects into the existing `[workspace]` members, keeping existing entries and
+/// their decoration. New entries are added in sorted position, or at the end, according to
+/// `order`. Existing entries that `classify` reports as missing are kept, commented out or removed
+/// according to `missing`.
+pub fn toml_merge<T, F>(
+    contents: &str,
+    sub_projects: &[T],
+    classify: F,
+    missing: &MissingMemberBehaviour,
+    order: &SortOrder,
+) -> Result<String, TomlError>
+where
+    T: AsRef<str>,
+    F: Fn(&str) -> ExistingEntry,
+{
+    let mut doc = contents.parse::<Document>()?;
+
+    let existing = match workspace_array(&doc, "members") {
+        Some(array) if !array.is_empty() && array.iter().all(|v| v.is_str()) => array.clone(),
+        _ => return toml_update(contents, sub_projects),
+    };
+
+    doc["workspace"]["members"] = value(merge_array(
+        &existing,
+        sub_projects,
+        classify,
+        missing,
+        order,
+    ));
+
+    Ok(doc.to_string())
+}
+
+/// Adds paths to the `[workspace]` exclude array, keeping existing entries and their decoration.
+pub fn toml_exclude<T>(
+    contents: &str,
+    excludes: &[T],
+    order: &SortOrder,
+) -> Result<String, TomlError>
+where
+    T: AsRef<str>,
+{
+    let mut doc = contents.parse::<Document>()?;
+
+    let array = match workspace_array(&doc, "exclude") {
+        Some(array) if !array.is_empty() && array.iter().all(|v| v.is_str()) => merge_array(
+            array,
+            excludes,
+            |_| ExistingEntry::Found,
+            &MissingMemberBehaviour::Keep,
+            order,
+        ),
+        _ if excludes.is_empty() => return Ok(doc.to_string()),
+        _ => new_array(excludes),
+    };
+
+    if doc["workspace"].is_none() {
+        doc["workspace"] = table();
+    }
+    doc["workspace"]["exclude"] = value(array);
+
+    Ok(doc.to_string())
+}
+
+/// Replaces sub projects that share a parent directory with a `<parent>/*` glob, when `expand`
+/// shows that the glob would match exactly those sub projects and nothing else.
+pub fn collapse_globs<F>(sub_projects: &[String], expand: F) -> Vec<String>
+where
+    F: Fn(&str) -> Vec<String>,
+{
+    let mut parents: Vec<&str> = sub_projects.iter().filter_map(|p| parent(p)).collect();
+    parents.sort();
+    parents.dedup();
+
+    let mut collapsed: Vec<String> = sub_projects.to_vec();
+
+    for parent_dir in parents {
+        let siblings: Vec<&String> = sub_projects
+            .iter()
+            .filter(|p| parent(p) == Some(parent_dir))
+            .collect();
+
+        if siblings.len() < 2 {
+            continue;
+        }
+
+        let glob = format!("{}/*", parent_dir);
+        let mut expanded = expand(&glob);
+        expanded.sort();
+
+        if expanded.iter().eq(siblings.iter().copied()) {
+            collapsed.retain(|p| parent(p) != Some(parent_dir));
+            collapsed.push(glob);
+        }
+    }
+
+    collapsed.sort();
+    collapsed
+}
+
+fn parent(path: &str) -> Option<&str> {
+    path.rfind('/').map(|index| &path[..index])
+}
+
+pub fn manifest_kind(contents: &str) -> Result<ManifestKind, TomlError> {
+    let doc = contents.parse::<Document>()?;
+    let has_table = |key| doc.as_table().get(key).is_some_and(Item::is_table_like);
+
+    Ok(if has_table("workspace") {
+        ManifestKind::Workspace
+    } else if has_table("package") || has_table("project") {
+        ManifestKind::Package
+    } else {
+        ManifestKind::Other
+    })
+}
+
+/// Whether a manifest has a package, whether or not it also has a `[workspace]` table.
+pub fn has_package(contents: &str) -> Result<bool, TomlError> {
+    Ok(matches!(
+        manifest_kind(&remove_workspace(contents)?)?,
+        ManifestKind::Package
+    ))
+}
+
+/// Reads the `[workspace]` resolver.
+pub fn read_resolver(contents: &str) -> Result<Option<String>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+
+    Ok(workspace_item(&doc, "resolver")
+        .and_then(|resolver| resolver.as_str())
+        .map(String::from))
+}
+
+/// Sets the `[workspace]` resolver.
+pub fn toml_resolver(contents: &str, resolver: &str) -> Result<String, TomlError> {
+    let mut doc = contents.parse::<Document>()?;
+
+    if doc["workspace"].is_none() {
+        doc["workspace"] = table();
+    }
+    doc["workspace"]["resolver"] = value(resolver);
+
+    Ok(doc.to_string())
+}
+
+/// Reads the string entries of the `[workspace]` exclude array.
+pub fn read_excludes(contents: &str) -> Result<Vec<String>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+
+    Ok(workspace_strings(&doc, "exclude"))
+}
+
+/// Reads the string entries of the `[workspace]` members array.
+pub fn read_members(contents: &str) -> Result<Vec<String>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+
+    Ok(workspace_strings(&doc, "members"))
+}
+
+/// Removes the `[workspace]` table, and any of its sub tables, from a manifest.
+pub fn remove_workspace(contents: &str) -> Result<String, TomlError> {
+    let mut doc = contents.parse::<Document>()?;
+
+    doc.as_table_mut().remove("workspace");
+
+    Ok(doc.to_string())
+}
+
+/// The settings of a workspace root that would be lost by removing its `[workspace]` table and
+/// making its members part of another workspace: `[workspace]` keys other than members,
+/// resolver and metadata, such as the tables members inherit from, and the `[patch]`,
+/// `[replace]` and `[profile]` tables that only apply at a workspace root.
+pub fn nested_workspace_settings(contents: &str) -> Result<Vec<String>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+    let mut settings = vec![];
+
+    if let Some(workspace) = doc["workspace"].as_table_like() {
+        for (key, item) in workspace.iter() {
+            if !item.is_none() && !["members", "resolver", "metadata"].contains(&key) {
+                settings.push(format!("workspace.{}", key));
+            }
+        }
+    }
+
+    for key in WORKSPACE_ROOT_TABLES
+        .iter()
+        .filter(|key| **key != "workspace")
+    {
+        if doc.as_table().contains_key(key) {
+            settings.push(key.to_string());
+        }
+    }
+
+    Ok(settings)
+}
+
+/// Splits a root manifest into a virtual manifest, holding the workspace level tables, and a
+/// manifest for its package moved `depth` directories down. Relative paths in the package are
+/// rebased onto the root unless `moved` reports that their first component moves with the
+/// package. Returns `None` if there is no package.
+pub fn split_root_package<F>(
+    contents: &str,
+    depth: usize,
+    moved: F,
+) -> Result<Option<(String, String)>, TomlError>
+where
+    F: Fn(&str) -> bool,
+{
+    if !has_package(contents)? {
+        return Ok(None);
+    }
+
+    let mut root = contents.parse::<Document>()?;
+    let mut package = contents.parse::<Document>()?;
+
+    let keys: Vec<String> = root.as_table().iter().map(|(k, _)| k.to_string()).collect();
+    for key in keys {
+        if WORKSPACE_ROOT_TABLES.contains(&key.as_str()) {
+            package.as_table_mut().remove(&key);
+        } else {
+            root.as_table_mut().remove(&key);
+        }
+    }
+
+    rebase_package_paths(package.as_table_mut(), depth, &moved);
+
+    Ok(Some((tidy(&root.to_string()), tidy(&package.to_string()))))
+}
+
+/// Drops the blank lines left around removed tables.
+fn tidy(contents: &str) -> String {
+    match contents.trim() {
+        "" => String::new(),
+        trimmed => format!("{}\n", trimmed),
+    }
+}
+
+fn rebase_package_paths<F>(package: &mut Table, depth: usize, moved: &F)
+where
+    F: Fn(&str) -> bool,
+{
+    for (table, key) in &[
+        ("package", "readme"),
+        ("package", "license-file"),
+        ("package", "build"),
+        ("project", "build"),
+        ("lib", "path"),
+    ] {
+        if let Some(table) = item_mut(package, table).and_then(|t| t.as_table_mut()) {
+            rebase_path(table, key, depth, moved);
+        }
+    }
+
+    for key in &TARGET_TABLES {
+        if let Some(targets) = item_mut(package, key).and_then(|t| t.as_array_of_tables_mut()) {
+            for index in 0..targets.len() {
+                if let Some(target) = targets.get_mut(index) {
+                    rebase_path(target, "path", depth, moved);
+                }
+            }
+        }
+    }
+
+    rebase_dependency_paths(package, depth, moved);
+
+    if let Some(targets) = item_mut(package, "target").and_then(|t| t.as_table_mut()) {
+        let cfgs: Vec<String> = targets.iter().map(|(k, _)| k.to_string()).collect();
+        for cfg in cfgs {
+            if let Some(target) = item_mut(targets, &cfg).and_then(|t| t.as_table_mut()) {
+                rebase_dependency_paths(target, depth, moved);
+            }
+        }
+    }
+}
+
+fn rebase_dependency_paths<F>(table: &mut Table, depth: usize, moved: &F)
+where
+    F: Fn(&str) -> bool,
+{
+    for key in &DEPENDENCY_TABLES {
+        let dependencies = match item_mut(table, key).and_then(|t| t.as_table_mut()) {
+            Some(dependencies) => dependencies,
+            None => continue,
+        };
+
+        let names: Vec<String> = dependencies.iter().map(|(k, _)| k.to_string()).collect();
+        for name in names {
+            match item_mut(dependencies, &name) {
+                Some(Item::Table(dependency)) => rebase_path(dependency, "path", depth, moved),
+                Some(Item::Value(Value::InlineTable(dependency))) => {
+                    if let Some(path) = dependency.get_mut("path") {
+                        rebase_value(path, depth, moved);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn rebase_path<F>(table: &mut Table, key: &str, depth: usize, moved: &F)
+where
+    F: Fn(&str) -> bool,
+{
+    if let Some(value) = item_mut(table, key).and_then(|v| v.as_value_mut()) {
+        rebase_value(value, depth, moved);
+    }
+}
+
+fn rebase_value<F>(value: &mut Value, depth: usize, moved: &F)
+where
+    F: Fn(&str) -> bool,
+{
+    let path = match value.as_str() {
+        Some(path) => path.trim_start_matches("./"),
+        None => return,
+    };
+
+    if Path::new(path).is_absolute() || moved(path.split('/').next().unwrap_or(path)) {
+        return;
+    }
+
+    let up = vec![".."; depth].join("/");
+    let rebased = if path == "." {
+        up
+    } else {
+        format!("{}/{}", up, path)
+    };
+
+    let prefix = value.decor().prefix().to_string();
+    let suffix = value.decor().suffix().to_string();
+    *value = decorated(string_value(&rebased), &prefix, &suffix);
+}
+
+/// The item at `key`, if the table has one. `Table::entry` would insert an empty item otherwise.
+fn item_mut<'a>(table: &'a mut Table, key: &str) -> Option<&'a mut Item> {
+    if table.contains_key(key) {
+        Some(table.entry(key))
+    } else {
+        None
+    }
+}
+
+/// Reads the dependencies of a member, with each requirement written the way it would appear in
+/// `[workspace.dependencies]`. A path dependency keeps its path, relative to the member.
+/// Dependencies that are already inherited are left out.
+pub fn read_dependencies(contents: &str) -> Result<Vec<(String, String)>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+
+    Ok(DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|key| doc.as_table().get(key).and_then(|t| t.as_table()))
+        .flat_map(dependency_requirements)
+        .collect())
+}
+
+/// Reads `[workspace.dependencies]`, written as [`read_dependencies`] writes requirements.
+pub fn read_workspace_dependencies(contents: &str) -> Result<Vec<(String, String)>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+
+    Ok(workspace_item(&doc, "dependencies")
+        .and_then(|t| t.as_table())
+        .map(dependency_requirements)
+        .unwrap_or_default())
+}
+
+/// Whether a requirement, as [`read_dependencies`] writes requirements, is a path dependency.
+pub fn is_path_requirement(requirement: &str) -> bool {
+    requirement
+        .parse::<Value>()
+        .is_ok_and(|v| v.as_inline_table().is_some_and(|t| t.contains_key("path")))
+}
+
+/// Reads the path dependencies of a member as name and path. Dependencies with keys besides
+/// `path`, `version` and the keys a member may set for itself are left out.
+pub fn read_path_dependencies(contents: &str) -> Result<Vec<(String, String)>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+
+    Ok(DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|key| doc.as_table().get(key).and_then(|t| t.as_table()))
+        .flat_map(path_dependencies)
+        .collect())
+}
+
+/// Reads the path dependencies in `[workspace.dependencies]` as name and path.
+pub fn read_workspace_path_dependencies(
+    contents: &str,
+) -> Result<Vec<(String, String)>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+
+    Ok(workspace_item(&doc, "dependencies")
+        .and_then(|t| t.as_table())
+        .map(path_dependencies)
+        .unwrap_or_default())
+}
+
+/// A path dependency requirement, as [`read_dependencies`] writes requirements.
+pub fn path_requirement(path: &str, version: Option<&str>) -> String {
+    match version {
+        Some(version) => format!(
+            "{{ path = {}, version = {} }}",
+            undecorated(&string_value(path)),
+            undecorated(&string_value(version))
+        ),
+        None => format!("{{ path = {} }}", undecorated(&string_value(path))),
+    }
+}
+
+/// Reads `version` from `[package]`, if it is a plain string.
+pub fn read_package_version(contents: &str) -> Result<Option<String>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+
+    Ok(doc
+        .as_table()
+        .get("package")
+        .and_then(|package| package.as_table())
+        .and_then(|package| package.get("version"))
+        .and_then(|version| version.as_str())
+        .map(String::from))
+}
+
+/// Adds dependencies, with requirements as read by [`read_dependencies`], to
+/// `[workspace.dependencies]`.
+pub fn toml_workspace_dependencies(
+    contents: &str,
+    dependencies: &[(String, String)],
+) -> Result<String, TomlError> {
+    let mut doc = contents.parse::<Document>()?;
+
+    if dependencies.is_empty() {
+        return Ok(doc.to_string());
+    }
+
+    if doc["workspace"].is_none() {
+        doc["workspace"] = table();
+    }
+    if workspace_item(&doc, "dependencies").is_none() {
+        doc["workspace"]["dependencies"] = table();
+    }
+
+    for (name, requirement) in dependencies {
+        doc["workspace"]["dependencies"][name.as_str()] = value(requirement.parse::<Value>()?);
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Rewrites the dependencies of a member that have the given name and requirement, as
+/// [`read_dependencies`] reads them, to `{ workspace = true }`, keeping the keys a member may set
+/// for itself.
+pub fn inherit_dependencies(
+    contents: &str,
+    dependencies: &[(String, String)],
+) -> Result<String, TomlError> {
+    inherit(contents, |name, item| {
+        dependencies
+            .iter()
+            .any(|(n, r)| n == name && dependency_requirement(item).as_ref() == Some(r))
+    })
+}
+
+/// Rewrites the path dependencies of a member that have the given name and path, as
+/// [`read_path_dependencies`] reads them, like [`inherit_dependencies`].
+pub fn inherit_path_dependencies(
+    contents: &str,
+    dependencies: &[(String, String)],
+) -> Result<String, TomlError> {
+    inherit(contents, |name, item| {
+        dependencies
+            .iter()
+            .any(|(n, p)| n == name && dependency_path(item).as_ref() == Some(p))
+    })
+}
+
+fn inherit<F>(contents: &str, inherits: F) -> Result<String, TomlError>
+where
+    F: Fn(&str, &Item) -> bool,
+{
+    let mut doc = contents.parse::<Document>()?;
+
+    for key in &DEPENDENCY_TABLES {
+        let dependencies = match item_mut(doc.as_table_mut(), key).and_then(|t| t.as_table_mut()) {
+            Some(dependencies) => dependencies,
+            None => continue,
+        };
+
+        let names: Vec<String> = dependencies
+            .iter()
+            .filter(|(name, item)| inherits(name, item))
+            .map(|(name, _)| name.to_string())
+            .collect();
+
+        for name in &names {
+            let item = match item_mut(dependencies, name) {
+                Some(item) => item,
+                None => continue,
+            };
+
+            match item {
+                Item::Table(dependency) => {
+                    let keys: Vec<String> = dependency
+                        .iter()
+                        .map(|(k, _)| k.to_string())
+                        .filter(|k| !MEMBER_DEPENDENCY_KEYS.contains(&k.as_str()))
+                        .collect();
+                    for key in keys {
+                        dependency.remove(&key);
+                    }
+                    dependency["workspace"] = value(true);
+                }
+                Item::Value(dependency) => {
+                    let mut inherited = InlineTable::default();
+                    inherited.get_or_insert("workspace", true);
+                    if let Some(original) = dependency.as_inline_table() {
+                        for (key, value) in original.iter() {
+                            if MEMBER_DEPENDENCY_KEYS.contains(&key) {
+                                inherited.get_or_insert(key, decorated(value.clone(), "", ""));
+                            }
+                        }
+                    }
+                    inherited.fmt();
+
+                    let prefix = dependency.decor().prefix().to_string();
+                    let suffix = dependency.decor().suffix().to_string();
+                    *item = Item::Value(decorated(Value::InlineTable(inherited), &prefix, &suffix));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Reads the inheritable fields of a member's `[package]`, each value written without decoration,
+/// or `None` if the field is already inherited from the workspace.
+pub fn read_package_fields(contents: &str) -> Result<Vec<(String, Option<String>)>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+
+    let package = match doc.as_table().get("package").and_then(|p| p.as_table()) {
+        Some(package) => package,
+        None => return Ok(vec![]),
+    };
+
+    Ok(INHERITABLE_PACKAGE_FIELDS
+        .iter()
+        .filter_map(|field| {
+            package.get(field).and_then(|item| match item.as_value() {
+                Some(Value::InlineTable(table)) if table.get("workspace").is_some() => {
+                    Some((field.to_string(), None))
+                }
+                Some(value) => Some((field.to_string(), Some(undecorated(value)))),
+                None => None,
+            })
+        })
+        .collect())
+}
+
+/// Reads `[workspace.package]`, written as [`read_package_fields`] writes values.
+pub fn read_workspace_package(contents: &str) -> Result<Vec<(String, String)>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+
+    Ok(workspace_item(&doc, "package")
+        .and_then(|t| t.as_table())
+        .map(|package| {
+            package
+                .iter()
+                .filter_map(|(field, item)| {
+                    item.as_value()
+                        .map(|value| (field.to_string(), undecorated(value)))
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Adds fields, with values as read by [`read_package_fields`], to `[workspace.package]`.
+pub fn toml_workspace_package(
+    contents: &str,
+    fields: &[(String, String)],
+) -> Result<String, TomlError> {
+    let mut doc = contents.parse::<Document>()?;
+
+    if fields.is_empty() {
+        return Ok(doc.to_string());
+    }
+
+    if doc["workspace"].is_none() {
+        doc["workspace"] = table();
+    }
+    if workspace_item(&doc, "package").is_none() {
+        doc["workspace"]["package"] = table();
+    }
+
+    for (field, field_value) in fields {
+        doc["workspace"]["package"][field.as_str()] = value(field_value.parse::<Value>()?);
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Rewrites the named `[package]` fields of a member to `{ workspace = true }`.
+pub fn inherit_package_fields(contents: &str, fields: &[String]) -> Result<String, TomlError> {
+    let mut doc = contents.parse::<Document>()?;
+
+    let package = match item_mut(doc.as_table_mut(), "package").and_then(|p| p.as_table_mut()) {
+        Some(package) => package,
+        None => return Ok(doc.to_string()),
+    };
+
+    for field in fields {
+        if let Some(value) = item_mut(package, field).and_then(|v| v.as_value_mut()) {
+            let mut inherited = InlineTable::default();
+            inherited.get_or_insert("workspace", true);
+            inherited.fmt();
+
+            let prefix = value.decor().prefix().to_string();
+            let suffix = value.decor().suffix().to_string();
+            *value = decorated(Value::InlineTable(inherited), &prefix, &suffix);
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+fn path_dependencies(dependencies: &Table) -> Vec<(String, String)> {
+    dependencies
+        .iter()
+        .filter_map(|(name, item)| dependency_path(item).map(|path| (name.to_string(), path)))
+        .collect()
+}
+
+/// A path dependency's path, or `None` if it has keys besides `path`, `version` and the keys a
+/// member may set for itself.
+fn dependency_path(item: &Item) -> Option<String> {
+    let keys: Vec<(&str, Option<&Value>)> = match item {
+        Item::Value(Value::InlineTable(dependency)) => {
+            dependency.iter().map(|(k, v)| (k, Some(v))).collect()
+        }
+        Item::Table(dependency) => dependency
+            .iter()
+            .map(|(k, item)| (k, item.as_value()))
+            .collect(),
+        _ => return None,
+    };
+
+    let only_path_keys = keys.iter().all(|(key, _)| {
+        *key == "path" || *key == "version" || MEMBER_DEPENDENCY_KEYS.contains(key)
+    });
+
+    keys.iter()
+        .find(|(key, _)| *key == "path")
+        .and_then(|(_, path)| path.and_then(|p| p.as_str()))
+        .filter(|_| only_path_keys)
+        .map(String::from)
+}
+
+/// Reads the settings under `[profile]` as key path below `profile` and value, written without
+/// decoration. Inline tables are read as a single value.
+pub fn read_profiles(contents: &str) -> Result<Vec<(Vec<String>, String)>, TomlError> {
+    let doc = contents.parse::<Document>()?;
+
+    let mut settings = vec![];
+    if let Some(profiles) = doc.as_table().get("profile").and_then(|p| p.as_table()) {
+        profile_settings(profiles, &mut vec![], &mut settings);
+    }
+
+    Ok(settings)
+}
+
+/// Sets profile settings, as read by [`read_profiles`], adding the tables they need.
+pub fn toml_profiles(
+    contents: &str,
+    settings: &[(Vec<String>, String)],
+) -> Result<String, TomlError> {
+    let mut doc = contents.parse::<Document>()?;
+
+    for (path, setting) in settings {
+        let mut path = path.clone();
+        path.insert(0, "profile".to_string());
+
+        set_nested(&mut doc, &path, setting.parse::<Value>()?);
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Removes the profile settings that are not in `keep`, and any tables left empty.
+pub fn remove_profiles(contents: &str, keep: &[Vec<String>]) -> Result<String, TomlError> {
+    let mut doc = contents.parse::<Document>()?;
+
+    let empty = match item_mut(doc.as_table_mut(), "profile").and_then(|p| p.as_table_mut()) {
+        Some(profiles) => remove_profile_settings(profiles, &mut vec![], keep),
+        None => return Ok(doc.to_string()),
+    };
+    if empty {
+        doc.as_table_mut().remove("profile");
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Reads the entries of the `[patch.*]` and `[replace]` tables as key path and source, written
+/// without decoration and with any `path` passed through `rebase`.
+pub fn read_patches<F>(contents: &str, rebase: F) -> Result<Vec<(Vec<String>, String)>, TomlError>
+where
+    F: Fn(&str) -> String,
+{
+    let doc = contents.parse::<Document>()?;
+
+    let mut tables = vec![];
+    if let Some(patches) = doc.as_table().get("patch").and_then(|p| p.as_table()) {
+        for (source, item) in patches.iter() {
+            if let Some(table) = item.as_table() {
+                tables.push((vec!["patch".to_string(), source.to_string()], table));
+            }
+        }
+    }
+    if let Some(replace) = doc.as_table().get("replace").and_then(|r| r.as_table()) {
+        tables.push((vec!["replace".to_string()], replace));
+    }
+
+    let mut patches = vec![];
+    for (path, table) in tables {
+        for (name, item) in table.iter() {
+            let mut entries: Vec<(String, String)> = match table_entries(item) {
+                Some(entries) => entries
+                    .into_iter()
+                    .map(|(key, value)| match value.as_str() {
+                        Some(path) if key == "path" => {
+                            let rebased = rebase(path);
+                            (key, undecorated(&string_value(&rebased)))
+                        }
+                        _ => (key, undecorated(value)),
+                    })
+                    .collect(),
+                None => continue,
+            };
+            entries.sort();
+
+            let mut path = path.clone();
+            path.push(name.to_string());
+            patches.push((path, inline_table(&entries)));
+        }
+    }
+
+    Ok(patches)
+}
+
+/// Adds `[patch.*]` and `[replace]` entries, as read by [`read_patches`].
+pub fn toml_patches(
+    contents: &str,
+    patches: &[(Vec<String>, String)],
+) -> Result<String, TomlError> {
+    let mut doc = contents.parse::<Document>()?;
+
+    for (path, source) in patches {
+        set_nested(&mut doc, path, source.parse::<Value>()?);
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Removes `[patch.*]` and `[replace]` entries, as read by [`read_patches`], and any tables left
+/// empty. Entries that could not be read are left alone.
+pub fn remove_patches(contents: &str, patches: &[Vec<String>]) -> Result<String, TomlError> {
+    let mut doc = contents.parse::<Document>()?;
+
+    for path in patches {
+        if let Some((name, tables)) = path.split_last() {
+            remove_nested(doc.as_table_mut(), tables, name);
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Removes a key below the tables on the path, and any of those tables left empty.
+fn remove_nested(table: &mut Table, tables: &[String], key: &str) {
+    let (first, rest) = match tables.split_first() {
+        Some(split) => split,
+        None => {
+            table.remove(key);
+            return;
+        }
+    };
+
+    let empty = match item_mut(table, first).and_then(|item| item.as_table_mut()) {
+        Some(sub_table) => {
+            remove_nested(sub_table, rest, key);
+            sub_table.is_empty()
+        }
+        None => false,
+    };
+    if empty {
+        table.remove(first);
+    }
+}
+
+fn profile_settings(
+    table: &Table,
+    path: &mut Vec<String>,
+    settings: &mut Vec<(Vec<String>, String)>,
+) {
+    for (key, item) in table.iter() {
+        path.push(key.to_string());
+        match item {
+            Item::Table(table) => profile_settings(table, path, settings),
+            Item::Value(setting) => settings.push((path.clone(), undecorated(setting))),
+            _ => {}
+        }
+        path.pop();
+    }
+}
+
+/// Returns true if the table is left empty.
+fn remove_profile_settings(
+    table: &mut Table,
+    path: &mut Vec<String>,
+    keep: &[Vec<String>],
+) -> bool {
+    let keys: Vec<String> = table.iter().map(|(k, _)| k.to_string()).collect();
+
+    for key in keys {
+        path.push(key.clone());
+        let remove = match item_mut(table, &key) {
+            Some(Item::Table(sub_table)) => remove_profile_settings(sub_table, path, keep),
+            Some(Item::Value(_)) => !keep.contains(path),
+            _ => false,
+        };
+        if remove {
+            table.remove(&key);
+        }
+        path.pop();
+    }
+
+    table.is_empty()
+}
+
+fn dependency_requirements(dependencies: &Table) -> Vec<(String, String)> {
+    dependencies
+        .iter()
+        .filter_map(|(name, item)| dependency_requirement(item).map(|r| (name.to_string(), r)))
+        .collect()
+}
+
+/// A dependency's requirement without decoration or the keys a member may set for itself,
+/// written the same way whichever form it was declared in.
+fn dependency_requirement(item: &Item) -> Option<String> {
+    let mut entries: Vec<(String, String)> = match item {
+        Item::Value(Value::InlineTable(_)) | Item::Table(_) => table_entries(item)?
+            .into_iter()
+            .map(|(key, value)| (key, undecorated(value)))
+            .collect(),
+        Item::Value(version) => return Some(undecorated(version)),
+        _ => return None,
+    };
+
+    if entries.iter().any(|(key, _)| key == "workspace") {
+        return None;
+    }
+
+    entries.retain(|(key, _)| !MEMBER_DEPENDENCY_KEYS.contains(&key.as_str()));
+    entries.sort();
+
+    match entries.as_slice() {
+        [] => None,
+        [(key, version)] if key == "version" => Some(version.clone()),
+        _ => Some(inline_table(&entries)),
+    }
+}
+
+/// The values of a table or inline table, or `None` for anything else.
+fn table_entries(item: &Item) -> Option<Vec<(String, &Value)>> {
+    match item {
+        Item::Value(Value::InlineTable(table)) => Some(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        ),
+        Item::Table(table) => Some(
+            table
+                .iter()
+                .filter_map(|(key, item)| item.as_value().map(|v| (key.to_string(), v)))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn inline_table(entries: &[(String, String)]) -> String {
+    format!(
+        "{{ {} }}",
+        entries
+            .iter()
+            .map(|(key, value)| format!("{} = {}", key, value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Sets a value below the document root, adding implicit tables on the way.
+fn set_nested(doc: &mut Document, path: &[String], setting: Value) {
+    let (first, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+
+    let mut item = &mut doc[first.as_str()];
+    for key in rest {
+        if item.is_none() {
+            let mut implicit = Table::new();
+            implicit.set_implicit(true);
+            *item = Item::Table(implicit);
+        }
+        item = &mut item[key.as_str()];
+    }
+
+    *item = value(setting);
+}
+
+/// A string written as a basic string, with `\`, `"` and control characters escaped, so that it
+/// reads back as the same text. `Value::from` picks a literal string for some text without
+/// escaping it, and panics on text that has both `'''` and `"""`.
+pub fn string_value(text: &str) -> Value {
+    let mut escaped = String::from("\"");
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+
+    escaped
+        .parse::<Value>()
+        .expect("Expect an escaped basic string to parse")
+}
+
+fn undecorated(value: &Value) -> String {
+    decorated(value.clone(), "", "").to_string()
+}
+
+fn new_array<T>(entries: &[T]) -> Array
+where
+    T: AsRef<str>,
+{
+    let mut array = Array::default();
+
+    for (index, entry) in entries.iter().enumerate() {
+        if index == entries.len() - 1 {
+            array
+                .push_formatted(decorated(
+                    string_value(entry.as_ref()),
+                    MEMBER_INDENT,
+                    ",\n",
+                ))
+                .unwrap();
+        } else {
+            array
+                .push_formatted(decorated(string_value(entry.as_ref()), MEMBER_INDENT, ""))
+                .unwrap();
+        }
+    }
+
+    array
+}
+
+/// Merges entries into an existing array of strings, see [`toml_merge`].
+fn merge_array<T, F>(
+    existing: &Array,
+    entries: &[T],
+    classify: F,
+    missing: &MissingMemberBehaviour,
+    order: &SortOrder,
+) -> Array
+where
+    T: AsRef<str>,
+    F: Fn(&str) -> ExistingEntry,
+{
+    let multi_line = existing.iter().any(|v| v.decor().prefix().contains('\n'));
+    let (new_head, new_rest) = if multi_line {
+        ("", MEMBER_INDENT)
+    } else {
+        (" ", "")
+    };
+
+    let mut new_entries = entries
+        .iter()
+        .filter(|p| !existing.iter().any(|v| v.as_str() == Some(p.as_ref())))
+        .peekable();
+
+    let mut members: Vec<Value> = vec![];
+    let mut comments = String::new();
+    let mut carried_head: Option<String> = None;
+    let mut comment_next_head = false;
+
+    for value in existing.iter() {
+        let entry = value.as_str().unwrap();
+        let (own_head, rest) = split_prefix(value.decor().prefix());
+
+        let mut head = match carried_head.take() {
+            Some(head) => {
+                // what followed a commented out entry's comma stays on its line
+                if comment_next_head {
+                    comments.push_str(own_head);
+                }
+                head
+            }
+            None => own_head.to_string(),
+        };
+        comment_next_head = false;
+
+        while *order == SortOrder::Sorted && new_entries.peek().is_some_and(|p| p.as_ref() < entry)
+        {
+            let new_entry = new_entries.next().unwrap();
+            members.push(member(
+                string_value(new_entry.as_ref()),
+                &head,
+                &mut comments,
+                new_rest,
+            ));
+            head = new_head.to_string();
+        }
+
+        let comment_out = match (classify(entry), missing) {
+            (ExistingEntry::Found, _) | (ExistingEntry::Missing, MissingMemberBehaviour::Keep) => {
+                members.push(member(value.clone(), &head, &mut comments, rest));
+                continue;
+            }
+            (ExistingEntry::Missing, MissingMemberBehaviour::CommentOut) => true,
+            _ => false,
+        };
+
+        if comment_out {
+            let rest = if rest.is_empty() { MEMBER_INDENT } else { rest };
+            comments.push_str(&format!("{}# {},", rest, undecorated(&string_value(entry))));
+            comment_next_head = true;
+        }
+        carried_head = Some(head);
+    }
+
+    let mut tail = array_tail(existing);
+    let mut follows_existing = carried_head.is_none();
+
+    for new_entry in new_entries {
+        let head = match (carried_head.take(), split_tail(&tail)) {
+            (Some(head), _) => head,
+            // a comment after the last existing entry stays on its line
+            (None, Some((comment, rest))) if follows_existing => {
+                tail = rest;
+                comment
+            }
+            _ => new_head.to_string(),
+        };
+        follows_existing = false;
+
+        members.push(member(
+            string_value(new_entry.as_ref()),
+            &head,
+            &mut comments,
+            new_rest,
+        ));
+    }
+
+    let trailing = format!("{}{}", carried_head.unwrap_or_default(), comments);
+
+    if members.is_empty() && trailing.is_empty() {
+        commented_array(EMPTY_MEMBERS_COMMENT_BLOCK)
+    } else if members.is_empty() {
+        commented_array(&format!("commented_array = [{}\n]\n", trailing))
+    } else {
+        let last = members.len() - 1;
+        let mut array = Array::default();
+
+        for (index, value) in members.into_iter().enumerate() {
+            let value = if index == last {
+                finish_last_member(value, &trailing, &tail)
+            } else {
+                value
+            };
+            array.push_formatted(value).unwrap();
+        }
+        array
+    }
+}
+
+/// Splits the decoration before an array entry into the part that finishes the previous line,
+/// e.g. a comment after the previous comma, and the part that belongs to the entry itself.
+fn split_prefix(prefix: &str) -> (&str, &str) {
+    match prefix.find('\n') {
+        Some(index) => prefix.split_at(index),
+        None => (prefix, ""),
+    }
+}
+
+/// Splits a comment on the same line as the last entry of an array from the rest of the array's
+/// tail, if there is one.
+fn split_tail(tail: &str) -> Option<(String, String)> {
+    let (comma, rest) = match tail.strip_prefix(',') {
+        Some(rest) => (",", rest),
+        None => ("", tail),
+    };
+    let (comment, rest) = split_prefix(rest);
+
+    if comment.contains('#') {
+        Some((comment.to_string(), format!("{}{}", comma, rest)))
+    } else {
+        None
+    }
+}
+
+/// Decorates a member with the head and rest of its prefix, with any pending comments between.
+fn member(value: Value, head: &str, comments: &mut String, rest: &str) -> Value {
+    let mut prefix = format!("{}{}", head, comments);
+    if !comments.is_empty() && !rest.starts_with('\n') {
+        prefix.push('\n');
+    }
+    prefix.push_str(rest);
+    comments.clear();
+
+    let suffix = value.decor().suffix().to_string();
+    decorated(value, &prefix, &suffix)
+}
+
+/// Gives the last member the array's closing decoration, with any trailing text left by entries
+/// that were not kept.
+fn finish_last_member(value: Value, trailing: &str, tail: &str) -> Value {
+    let mut suffix = value.decor().suffix().to_string();
+
+    if trailing.is_empty() {
+        suffix.push_str(tail);
+    } else {
+        let tail = tail.strip_prefix(',').unwrap_or(tail);
+        suffix.push(',');
+        suffix.push_str(trailing);
+        if !tail.contains('\n') {
+            suffix.push('\n');
+        }
+        suffix.push_str(tail);
+    }
+
+    let prefix = value.decor().prefix().to_string();
+    decorated(value, &prefix, &suffix)
+}
+
+/// The text between the last value of an array and its closing bracket, including any trailing
+/// comma.
+fn array_tail(array: &Array) -> String {
+    let rendered = decorated(Value::from(array.clone()), "", "").to_string();
+    let values = array
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    rendered[1 + values.len()..rendered.len() - 1].to_string()
+}
+
+fn commented_array(block: &str) -> Array {
+    let doc = block.parse::<Document>().expect("invalid doc");
+    doc["commented_array"].as_array().unwrap().clone()
+}
+
+fn workspace_strings(doc: &Document, key: &str) -> Vec<String> {
+    workspace_array(doc, key)
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn workspace_array<'a>(doc: &'a Document, key: &str) -> Option<&'a Array> {
+    workspace_item(doc, key).and_then(|array| array.as_array())
+}
+
+fn workspace_item<'a>(doc: &'a Document, key: &str) -> Option<&'a Item> {
+    doc.as_table()
+        .get("workspace")
+        .and_then(|workspace| workspace.as_table())
+        .and_then(|workspace| workspace.get(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_members() {
+        let toml = "";
+
+        let new_toml = toml_update::<String>(toml, &[]).unwrap();
+
+        assert_eq!(
+            new_toml,
+            r#"[workspace]
+
+members = [
+    # List your crates here, e.g:
+    # \"my-lib\",
+]
+"#
+        );
+    }
+
+    #[test]
+    fn one_member() {
+        let toml = "";
+
+        let new_toml = toml_update(toml, vec!["lib1"].as_slice()).unwrap();
+
+        assert_eq!(
+            new_toml,
+            r#"[workspace]
+
+members = [
+    "lib1",
+]
+"#
+        );
+    }
+
+    #[test]
+    fn two_members() {
+        let toml = "";
+
+        let new_toml = toml_update(toml, vec!["lib1", "lib2"].as_slice()).unwrap();
+
+        assert_eq!(
+            new_toml,
+            r#"[workspace]
+
+members = [
+    "lib1",
+    "lib2",
+]
+"#
+        );
+    }
+
+    #[test]
+    fn modify_existing_doc() {
+        let toml = r#"[table]
+value = [1, 2, 3]
+"#;
+
+        let new_toml = toml_update(toml, vec!["lib1"].as_slice()).unwrap();
+
+        assert_eq!(
+            new_toml,
+            r#"[table]
+value = [1, 2, 3]
+
+[workspace]
+members = [
+    "lib1",
+]
+"#
+        );
+    }
+
+    #[test]
+    fn modify_existing_doc_with_own_libs() {
+        let toml = r#"[table]
+value = [1, 2, 3]
+
+[workspace]
+members = ["libx",]
+somekey = "hi" # some random comment
+"#;
+
+        let new_toml = toml_update(toml, vec!["liba"].as_slice()).unwrap();
+
+        assert_eq!(
+            new_toml,
+            r#"[table]
+value = [1, 2, 3]
+
+[workspace]
+members = [
+    "liba",
+]
+somekey = "hi" # some random comment
+"#
+        );
+    }
+
+    #[test]
+    fn read_members_of_existing_doc() {
+        let toml = r#"[workspace]
+members = [
+    "lib1",
+    # "commented",
+    "lib2",
+]
+"#;
+
+        let members = read_members(toml).unwrap();
+
+        assert_eq!(vec!["lib1", "lib2"], members);
+    }
+
+    #[test]
+    fn read_members_of_doc_without_workspace() {
+        let members = read_members("[package]\nname = \"a\"\n").unwrap();
+
+        assert!(members.is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_existing_entries_and_adds_new_in_sorted_position() {
+        let toml = r#"[workspace]
+members = [
+    "b", # the b crate
+    # handwritten
+    "d",
+    "crates/*",
+]
+"#;
+
+        let new_toml = toml_merge(
+            toml,
+            vec!["a", "b", "c", "d", "e"].as_slice(),
+            |_| ExistingEntry::Found,
+            &MissingMemberBehaviour::Remove,
+            &SortOrder::Sorted,
+        )
+        .unwrap();
+
+        assert_eq!(
+            new_toml,
+            r#"[workspace]
+members = [
+    "a",
+    "b", # the b crate
+    "c",
+    # handwritten
+    "d",
+    "crates/*",
+    "e",
+]
+"#
+        );
+    }
+
+    #[test]
+    fn merge_appends_new_entries_after_existing() {
+        let toml = r#"[workspace]
+members = [
+    "d",
+    "b", # the b crate
+]
+"#;
+
+        let new_toml = toml_merge(
+            toml,
+            vec!["a", "b", "c", "d"].as_slice(),
+            |_| ExistingEntry::Found,
+            &MissingMemberBehaviour::Keep,
+            &SortOrder::Append,
+        )
+        .unwrap();
+
+        assert_eq!(
+            new_toml,
+            r#"[workspace]
+members = [
+    "d",
+    "b", # the b crate
+    "a",
+    "c",
+]
+"#
+        );
+    }
+
+    #[test]
+    fn merge_into_empty_members() {
+        let toml = r#"[workspace]
+
+members = [
+    # List your crates here, e.g:
+    # \"my-lib\",
+]
+"#;
+
+        let new_toml = toml_merge(
+            toml,
+            vec!["lib1"].as_slice(),
+            |_| ExistingEntry::Found,
+            &MissingMemberBehaviour::Keep,
+            &SortOrder::Sorted,
+        )
+        .unwrap();
+
+        assert_eq!(
+            new_toml,
+            r#"[workspace]
+
+members = [
+    "lib1",
+]
+"#
+        );
+    }
+
+    #[test]
+    fn merge_keeps_missing_members() {
+        let toml = r#"[workspace]
+members = [
+    "gone",
+    "lib1",
+]
+"#;
 
-    for (index, lib) in sub_projects.iter().enumerate() {
-        if index == sub_projects.len() - 1 {
-            array
-                .push_formatted(decorated(lib.clone().into(), "\n    ", ",\n"))
-                .unwrap();
-        } else {
-            array
-                .push_formatted(decorated(lib.clone().into(), "\n    ", ""))
-                .unwrap();
-        }
-    }
+        let new_toml = toml_merge(
+            toml,
+            vec!["lib1"].as_slice(),
+            |m| missing_if(m == "gone"),
+            &MissingMemberBehaviour::Keep,
+            &SortOrder::Sorted,
+        )
+        .unwrap();
 
-    if sub_projects.is_empty() {
-        let doc = EMPTY_MEMBERS_COMMENT_BLOCK
-            .parse::<Document>()
-            .expect("invalid doc");
-        array = doc["commented_array"].as_array().unwrap().clone();
+        assert_eq!(new_toml, toml);
     }
 
-    if doc["workspace"].is_none() {
-        doc["workspace"] = table();
-    }
-    doc["workspace"]["members"] = value(array);
+    #[test]
+    fn merge_removes_missing_members() {
+        let toml = r#"[workspace]
+members = [
+    "a",
+    "gone", # old crate
+    "z",
+    "gone2",
+]
+"#;
 
-    Ok(doc.to_string())
-}
+        let new_toml = toml_merge(
+            toml,
+            vec!["a", "z"].as_slice(),
+            |m| missing_if(m.starts_with("gone")),
+            &MissingMemberBehaviour::Remove,
+            &SortOrder::Sorted,
+        )
+        .unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        assert_eq!(
+            new_toml,
+            r#"[workspace]
+members = [
+    "a",
+    "z",
+]
+"#
+        );
+    }
 
     #[test]
-    fn no_members() {
-        let toml = "";
+    fn merge_comments_out_missing_members() {
+        let toml = r#"[workspace]
+members = [
+    "a",
+    "gone", # old crate
+    "z",
+    "gone2",
+]
+"#;
 
-        let new_toml = toml_update::<String>(toml, &[]).unwrap();
+        let new_toml = toml_merge(
+            toml,
+            vec!["a", "z"].as_slice(),
+            |m| missing_if(m.starts_with("gone")),
+            &MissingMemberBehaviour::CommentOut,
+            &SortOrder::Sorted,
+        )
+        .unwrap();
 
         assert_eq!(
             new_toml,
             r#"[workspace]
-
 members = [
-    # List your crates here, e.g:
-    # \"my-lib\",
+    "a",
+    # "gone", # old crate
+    "z",
+    # "gone2",
 ]
 "#
         );
     }
 
     #[test]
-    fn one_member() {
-        let toml = "";
+    fn merge_comments_out_all_members() {
+        let toml = r#"[workspace]
+members = ["gone"]
+"#;
 
-        let new_toml = toml_update(toml, vec!["lib1"].as_slice()).unwrap();
+        let new_toml = toml_merge::<&str, _>(
+            toml,
+            &[],
+            |_| ExistingEntry::Missing,
+            &MissingMemberBehaviour::CommentOut,
+            &SortOrder::Sorted,
+        )
+        .unwrap();
 
         assert_eq!(
             new_toml,
             r#"[workspace]
-
 members = [
-    "lib1",
+    # "gone",
 ]
 "#
         );
     }
 
     #[test]
-    fn two_members() {
-        let toml = "";
+    fn exclude_added_to_doc() {
+        let toml = r#"[workspace]
+members = [
+    "lib1",
+]
+"#;
 
-        let new_toml = toml_update(toml, vec!["lib1", "lib2"].as_slice()).unwrap();
+        let new_toml = toml_exclude(
+            toml,
+            vec!["scratch", "vendor"].as_slice(),
+            &SortOrder::Sorted,
+        )
+        .unwrap();
 
         assert_eq!(
             new_toml,
             r#"[workspace]
-
 members = [
     "lib1",
-    "lib2",
+]
+exclude = [
+    "scratch",
+    "vendor",
 ]
 "#
         );
     }
 
     #[test]
-    fn modify_existing_doc() {
-        let toml = r#"[table]
-value = [1, 2, 3]
+    fn exclude_merged_with_existing_excludes() {
+        let toml = r#"[workspace]
+exclude = [
+    "vendor", # third party
+]
 "#;
 
-        let new_toml = toml_update(toml, vec!["lib1"].as_slice()).unwrap();
+        let new_toml = toml_exclude(
+            toml,
+            vec!["scratch", "vendor"].as_slice(),
+            &SortOrder::Sorted,
+        )
+        .unwrap();
 
         assert_eq!(
             new_toml,
-            r#"[table]
-value = [1, 2, 3]
+            r#"[workspace]
+exclude = [
+    "scratch",
+    "vendor", # third party
+]
+"#
+        );
+        assert_eq!(vec!["scratch", "vendor"], read_excludes(&new_toml).unwrap());
+    }
 
-[workspace]
+    #[test]
+    fn no_excludes_leaves_doc_alone() {
+        let toml = "[workspace]\nmembers = []\n";
+
+        let new_toml = toml_exclude::<&str>(toml, &[], &SortOrder::Sorted).unwrap();
+
+        assert_eq!(new_toml, toml);
+    }
+
+    #[test]
+    fn merge_removes_covered_members() {
+        let toml = r#"[workspace]
+members = [
+    "crates/a",
+    "crates/b", # the b crate
+    "lib1",
+]
+"#;
+
+        let new_toml = toml_merge(
+            toml,
+            vec!["crates/*", "lib1"].as_slice(),
+            |m| {
+                if m.starts_with("crates/") {
+                    ExistingEntry::Covered
+                } else {
+                    ExistingEntry::Found
+                }
+            },
+            &MissingMemberBehaviour::CommentOut,
+            &SortOrder::Sorted,
+        )
+        .unwrap();
+
+        assert_eq!(
+            new_toml,
+            r#"[workspace]
 members = [
+    "crates/*",
     "lib1",
 ]
 "#
@@ -132,28 +1718,447 @@ members = [
     }
 
     #[test]
-    fn modify_existing_doc_with_own_libs() {
-        let toml = r#"[table]
-value = [1, 2, 3]
+    fn collapse_siblings_into_glob() {
+        let sub_projects = strings(&["crates/a", "crates/b", "lib1", "tools/x", "tools/y"]);
+
+        let collapsed = collapse_globs(&sub_projects, |glob| match glob {
+            "crates/*" => strings(&["crates/b", "crates/a"]),
+            _ => strings(&["tools/x", "tools/y", "tools/README.md"]),
+        });
+
+        assert_eq!(
+            strings(&["crates/*", "lib1", "tools/x", "tools/y"]),
+            collapsed
+        );
+    }
+
+    #[test]
+    fn do_not_collapse_single_crate() {
+        let sub_projects = strings(&["crates/a", "lib1"]);
+
+        let collapsed = collapse_globs(&sub_projects, |_| strings(&["crates/a"]));
+
+        assert_eq!(sub_projects, collapsed);
+    }
+
+    fn missing_if(missing: bool) -> ExistingEntry {
+        if missing {
+            ExistingEntry::Missing
+        } else {
+            ExistingEntry::Found
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn manifest_kinds() {
+        let kind = |toml| manifest_kind(toml).unwrap();
+
+        assert!(matches!(
+            kind("[package]\nname = \"a\"\n"),
+            ManifestKind::Package
+        ));
+        assert!(matches!(
+            kind("[workspace]\nmembers = []\n"),
+            ManifestKind::Workspace
+        ));
+        assert!(matches!(
+            kind("[package]\n[workspace]\n"),
+            ManifestKind::Workspace
+        ));
+        assert!(matches!(kind("[dependencies]\n"), ManifestKind::Other));
+        assert!(manifest_kind("bad toml!").is_err());
+    }
+
+    #[test]
+    fn remove_workspace_tables() {
+        let toml = "[package]\nname = \"a\"\n\n[workspace]\nmembers = [\"b\"]\n\n[workspace.metadata]\nx = 1\n";
+
+        let result = remove_workspace(toml).unwrap();
+
+        assert_eq!(result.trim(), "[package]\nname = \"a\"");
+        assert!(matches!(
+            manifest_kind(&result).unwrap(),
+            ManifestKind::Package
+        ));
+    }
+
+    #[test]
+    fn nested_workspace_settings_lost_by_absorbing() {
+        let toml = r#"[workspace]
+members = ["a"]
+resolver = "2"
+exclude = ["b"]
+
+[workspace.package]
+edition = "2021"
+
+[workspace.metadata]
+x = 1
+
+[patch.crates-io]
+c = { path = "c" }
+
+[profile.release]
+lto = true
+"#;
+
+        assert_eq!(
+            vec!["workspace.exclude", "workspace.package", "patch", "profile"],
+            nested_workspace_settings(toml).unwrap()
+        );
+        assert!(
+            nested_workspace_settings("[workspace]\nmembers = [\"a\"]\n")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn split_root_package_rebases_paths() {
+        let toml = r#"[package]
+name = "a"
+readme = "README.md"
+build = "build.rs"
+
+[dependencies]
+b = { path = "b", version = "1" }
+c = "1"
+
+[dependencies.d]
+path = "./d"
 
 [workspace]
-members = ["libx",]
-somekey = "hi" # some random comment
+members = ["b"]
+
+[profile.release]
+lto = true
 "#;
 
-        let new_toml = toml_update(toml, vec!["liba"].as_slice()).unwrap();
+        let (root, package) = split_root_package(toml, 1, |entry| entry == "build.rs")
+            .unwrap()
+            .unwrap();
 
         assert_eq!(
-            new_toml,
-            r#"[table]
-value = [1, 2, 3]
+            "[workspace]\nmembers = [\"b\"]\n\n[profile.release]\nlto = true\n",
+            root
+        );
+        assert_eq!(
+            r#"[package]
+name = "a"
+readme = "../README.md"
+build = "build.rs"
 
-[workspace]
-members = [
-    "liba",
-]
-somekey = "hi" # some random comment
-"#
+[dependencies]
+b = { path = "../b", version = "1" }
+c = "1"
+
+[dependencies.d]
+path = "../d"
+"#,
+            package
+        );
+    }
+
+    #[test]
+    fn split_root_package_needs_a_package() {
+        assert!(
+            split_root_package("[workspace]\nmembers = []\n", 1, |_| false)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn strings_read_back_as_written() {
+        for text in &["'x'", "libs/a\\nb", "a'''\"\"\"b", "tab\tbed", "bell\u{7}"] {
+            assert_eq!(Some(*text), string_value(text).as_str());
+
+            let requirement = path_requirement(text, None).parse::<Value>().unwrap();
+            assert_eq!(
+                Some(*text),
+                requirement
+                    .as_inline_table()
+                    .unwrap()
+                    .get("path")
+                    .unwrap()
+                    .as_str()
+            );
+        }
+
+        assert_eq!(
+            "\"a\\\\b\\\"c\\u0007\"",
+            undecorated(&string_value("a\\b\"c\u{7}"))
+        );
+    }
+
+    #[test]
+    fn read_dependency_requirements() {
+        let toml = r#"[dependencies]
+a = "1.0" # comment
+b = { version = "2", features = ["x"], optional = true }
+c = { path = "../c" }
+d = { workspace = true }
+
+[dev-dependencies.e]
+version = "3"
+default-features = false
+"#;
+
+        assert_eq!(
+            vec![
+                ("a".to_string(), "\"1.0\"".to_string()),
+                ("b".to_string(), "\"2\"".to_string()),
+                ("c".to_string(), "{ path = \"../c\" }".to_string()),
+                (
+                    "e".to_string(),
+                    "{ default-features = false, version = \"3\" }".to_string()
+                ),
+            ],
+            read_dependencies(toml).unwrap()
+        );
+    }
+
+    #[test]
+    fn add_workspace_dependencies() {
+        let toml = "[workspace]\nmembers = []\n";
+        let dependencies = vec![
+            ("a".to_string(), "\"1.0\"".to_string()),
+            (
+                "b".to_string(),
+                "{ default-features = false, version = \"3\" }".to_string(),
+            ),
+        ];
+
+        let result = toml_workspace_dependencies(toml, &dependencies).unwrap();
+
+        assert_eq!(dependencies, read_workspace_dependencies(&result).unwrap());
+    }
+
+    #[test]
+    fn inherit_member_dependencies() {
+        let toml = r#"[dependencies]
+a = "1.0"
+b = { version = "2", features = ["x"], optional = true }
+c = "1"
+
+[dev-dependencies.d]
+version = "3"
+features = ["y"]
+
+[build-dependencies]
+a = { path = "../a" }
+"#;
+        let dependencies = vec![
+            ("a".to_string(), "\"1.0\"".to_string()),
+            ("b".to_string(), "\"2\"".to_string()),
+            ("c".to_string(), "\"2\"".to_string()),
+            ("d".to_string(), "\"3\"".to_string()),
+        ];
+
+        let result = inherit_dependencies(toml, &dependencies).unwrap();
+
+        assert_eq!(
+            r#"[dependencies]
+a = { workspace = true }
+b = { workspace = true, features = ["x"], optional = true }
+c = "1"
+
+[dev-dependencies.d]
+features = ["y"]
+workspace = true
+
+[build-dependencies]
+a = { path = "../a" }
+"#,
+            result
+        );
+    }
+
+    #[test]
+    fn read_member_package_fields() {
+        let toml = r#"[package]
+name = "a"
+edition = "2018" # comment
+authors = ["A <a@example.com>"]
+license = { workspace = true }
+"#;
+
+        assert_eq!(
+            vec![
+                ("edition".to_string(), Some("\"2018\"".to_string())),
+                ("license".to_string(), None),
+                (
+                    "authors".to_string(),
+                    Some("[\"A <a@example.com>\"]".to_string())
+                ),
+            ],
+            read_package_fields(toml).unwrap()
+        );
+    }
+
+    #[test]
+    fn inherit_member_package_fields() {
+        let toml = "[package]\nname = \"a\"\nedition = \"2018\"\nlicense = \"MIT\"\n";
+        let fields = vec![("edition".to_string(), "\"2018\"".to_string())];
+
+        let member = inherit_package_fields(toml, &strings(&["edition"])).unwrap();
+        let root = toml_workspace_package("[workspace]\nmembers = []\n", &fields).unwrap();
+
+        assert_eq!(
+            "[package]\nname = \"a\"\nedition = { workspace = true }\nlicense = \"MIT\"\n",
+            member
+        );
+        assert_eq!(fields, read_workspace_package(&root).unwrap());
+    }
+
+    #[test]
+    fn read_member_path_dependencies() {
+        let toml = r#"[dependencies]
+a = { path = "../a", version = "0.1", features = ["x"] }
+b = { path = "../b", package = "c" }
+d = "1"
+
+[dev-dependencies.e]
+path = "../e"
+"#;
+
+        assert_eq!(
+            vec![
+                ("a".to_string(), "../a".to_string()),
+                ("e".to_string(), "../e".to_string()),
+            ],
+            read_path_dependencies(toml).unwrap()
+        );
+    }
+
+    #[test]
+    fn add_workspace_path_dependencies() {
+        let dependencies = vec![
+            ("a".to_string(), path_requirement("crates/a", Some("0.1.0"))),
+            ("b".to_string(), path_requirement("b", None)),
+        ];
+
+        let result = toml_workspace_dependencies("", &dependencies).unwrap();
+
+        assert_eq!(
+            vec![
+                ("a".to_string(), "crates/a".to_string()),
+                ("b".to_string(), "b".to_string()),
+            ],
+            read_workspace_path_dependencies(&result).unwrap()
+        );
+        assert!(result.contains("a = { path = \"crates/a\", version = \"0.1.0\" }"));
+    }
+
+    #[test]
+    fn set_resolver() {
+        let result = toml_resolver(EMPTY_FILE_TEMPLATE, "2").unwrap();
+
+        assert_eq!(Some("2".to_string()), read_resolver(&result).unwrap());
+        assert_eq!(None, read_resolver(EMPTY_FILE_TEMPLATE).unwrap());
+    }
+
+    #[test]
+    fn move_profile_settings() {
+        let member = r#"[package]
+name = "a"
+
+[profile.release]
+lto = true
+opt-level = 3
+
+[profile.dev.package.b]
+opt-level = 2
+"#;
+        let settings = read_profiles(member).unwrap();
+
+        assert_eq!(
+            vec![
+                (strings(&["release", "lto"]), "true".to_string()),
+                (strings(&["release", "opt-level"]), "3".to_string()),
+                (
+                    strings(&["dev", "package", "b", "opt-level"]),
+                    "2".to_string()
+                ),
+            ],
+            settings
+        );
+
+        let root = toml_profiles(EMPTY_FILE_TEMPLATE, &settings).unwrap();
+        assert_eq!(settings, read_profiles(&root).unwrap());
+
+        let kept = remove_profiles(member, &[strings(&["release", "lto"])]).unwrap();
+        assert_eq!(
+            "[package]\nname = \"a\"\n\n[profile.release]\nlto = true\n",
+            kept
+        );
+        assert_eq!(
+            "[package]\nname = \"a\"\n",
+            remove_profiles(member, &[]).unwrap()
+        );
+    }
+
+    #[test]
+    fn move_patches() {
+        let member = r#"[package]
+name = "a"
+
+[patch.crates-io]
+b = { path = "../b" }
+c = { git = "https://example.com/c", branch = "main" }
+
+[replace]
+"d:0.1.0" = { path = "d" }
+"#;
+        let patches = read_patches(member, |path| format!("a/{}", path)).unwrap();
+
+        assert_eq!(
+            vec![
+                (
+                    strings(&["patch", "crates-io", "b"]),
+                    "{ path = \"a/../b\" }".to_string()
+                ),
+                (
+                    strings(&["patch", "crates-io", "c"]),
+                    "{ branch = \"main\", git = \"https://example.com/c\" }".to_string()
+                ),
+                (
+                    strings(&["replace", "d:0.1.0"]),
+                    "{ path = \"a/d\" }".to_string()
+                ),
+            ],
+            patches
+        );
+
+        let root = toml_patches(EMPTY_FILE_TEMPLATE, &patches).unwrap();
+        assert_eq!(
+            patches,
+            read_patches(&root, |path| path.to_string()).unwrap()
+        );
+        let paths: Vec<Vec<String>> = patches.into_iter().map(|(path, _)| path).collect();
+        assert_eq!(
+            "[package]\nname = \"a\"\n",
+            remove_patches(member, &paths).unwrap()
+        );
+    }
+
+    #[test]
+    fn remove_patches_keeps_entries_that_were_not_read() {
+        let member = "[patch.crates-io]\nb = { path = \"b\" }\nc = \"1.0\"\n";
+        let paths: Vec<Vec<String>> = read_patches(member, |path| path.to_string())
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+
+        assert_eq!(vec![strings(&["patch", "crates-io", "b"])], paths);
+        assert_eq!(
+            "[patch.crates-io]\nc = \"1.0\"\n",
+            remove_patches(member, &paths).unwrap()
         );
     }
 }
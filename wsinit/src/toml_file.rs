@@ -21,6 +21,6 @@ impl Deref for TomlFile {
 
 impl Display for TomlFile {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        write!(f, "{}", self.to_str().unwrap())
+        write!(f, "{}", self.display())
     }
 }
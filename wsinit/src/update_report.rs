@@ -0,0 +1,309 @@
+use std::error::Error as StdError;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::error::{Error, Operation};
+use crate::skipped_crate::SkippedCrate;
+use crate::toml_changes::TomlChanges;
+use crate::toml_file::TomlFile;
+
+/// What an update did to the workspace toml file, or why it failed, in a form tools can read.
+#[derive(Clone, Debug)]
+pub struct UpdateReport {
+    toml: TomlFile,
+    action: Option<Action>,
+    before: Vec<String>,
+    after: Vec<String>,
+    added: Vec<String>,
+    removed: Vec<String>,
+    contents: Option<String>,
+    skipped: Vec<SkippedCrate>,
+    warnings: Vec<String>,
+    deleted_targets: Vec<PathBuf>,
+    dry_run: bool,
+    error: Option<ReportedError>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// There was no toml file, and one was written.
+    Created,
+
+    /// The toml file was rewritten, or a member manifest changed.
+    Updated,
+
+    /// The toml file already had the contents the update would write.
+    Unchanged,
+
+    /// The toml file was restored from its backup.
+    Restored,
+}
+
+/// An [`Error`] reduced to an identifier, the path involved and a message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReportedError {
+    kind: &'static str,
+    path: PathBuf,
+    message: String,
+}
+
+impl UpdateReport {
+    /// Reports the changes an update wrote, or in a dry run would have written.
+    pub(crate) fn new(changes: &TomlChanges, dry_run: bool) -> UpdateReport {
+        let action = if !changes.is_stale() {
+            Action::Unchanged
+        } else if changes.current().is_none() {
+            Action::Created
+        } else {
+            Action::Updated
+        };
+
+        let mut warnings: Vec<String> = changes
+            .conflicts()
+            .iter()
+            .map(|conflict| format!("Left in members {}", conflict))
+            .collect();
+        warnings.extend(
+            changes
+                .external_path_dependencies()
+                .iter()
+                .map(|dependency| format!("Path dependency {}", dependency)),
+        );
+        warnings.extend(
+            changes
+                .discovery_warnings()
+                .iter()
+                .map(|warning| format!("Could not search {}", warning)),
+        );
+        warnings.extend(
+            changes
+                .resolver_mismatch()
+                .map(|mismatch| format!("Workspace {}", mismatch)),
+        );
+
+        UpdateReport {
+            toml: changes.toml().clone(),
+            action: Some(action),
+            before: changes.before().to_vec(),
+            after: changes.after().to_vec(),
+            added: changes.added().to_vec(),
+            removed: changes.removed().to_vec(),
+            contents: Some(changes.proposed().to_string()),
+            skipped: changes.skipped().to_vec(),
+            warnings,
+            deleted_targets: vec![],
+            dry_run,
+            error: None,
+        }
+    }
+
+    /// Reports restoring the toml file from its backup, as changes from the replaced contents to
+    /// the restored ones.
+    pub(crate) fn restored(changes: &TomlChanges) -> UpdateReport {
+        UpdateReport {
+            action: Some(Action::Restored),
+            ..UpdateReport::new(changes, false)
+        }
+    }
+
+    pub(crate) fn failed(toml: &TomlFile, error: &Error) -> UpdateReport {
+        UpdateReport {
+            toml: toml.clone(),
+            action: None,
+            before: vec![],
+            after: vec![],
+            added: vec![],
+            removed: vec![],
+            contents: None,
+            skipped: vec![],
+            warnings: vec![],
+            deleted_targets: vec![],
+            dry_run: false,
+            error: Some(ReportedError::new(error)),
+        }
+    }
+
+    /// Adds the member target directories deleted after the update, and the error that stopped
+    /// the deleting, if any.
+    pub(crate) fn with_deleted_targets(
+        mut self,
+        deleted: Vec<PathBuf>,
+        error: Option<&Error>,
+    ) -> UpdateReport {
+        self.deleted_targets = deleted;
+        self.error = error.map(ReportedError::new);
+        self
+    }
+
+    pub fn toml(&self) -> &TomlFile {
+        &self.toml
+    }
+
+    /// What happened to the toml file, or would have in a dry run. None if the update failed.
+    pub fn action(&self) -> Option<Action> {
+        self.action
+    }
+
+    /// The members listed in the file before the update, in file order.
+    pub fn before(&self) -> &[String] {
+        &self.before
+    }
+
+    /// The members listed in the file after the update, in file order.
+    pub fn after(&self) -> &[String] {
+        &self.after
+    }
+
+    /// Members in the written file that were not in the previous file.
+    pub fn added(&self) -> &[String] {
+        &self.added
+    }
+
+    /// Members in the previous file that are not in the written file.
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    /// Members in both the previous and the written file.
+    pub fn kept(&self) -> Vec<&str> {
+        self.after
+            .iter()
+            .filter(|member| !self.added.contains(member))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// The contents of the toml file after the update. None if the update failed.
+    pub fn contents(&self) -> Option<&str> {
+        self.contents.as_deref()
+    }
+
+    /// Directories with a `Cargo.toml` that were not added as members, and why.
+    pub fn skipped(&self) -> &[SkippedCrate] {
+        &self.skipped
+    }
+
+    /// Conflicting settings, external path dependencies, paths that could not be searched and
+    /// resolver mismatches, as messages.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// The member target directories that were deleted.
+    pub fn deleted_targets(&self) -> &[PathBuf] {
+        &self.deleted_targets
+    }
+
+    /// True if nothing was written, so the action is what an update would do.
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Why the update failed, if it did.
+    pub fn error(&self) -> Option<&ReportedError> {
+        self.error.as_ref()
+    }
+
+    /// The report as a single line JSON object.
+    pub fn to_json(&self) -> String {
+        let path = self.toml.to_string_lossy();
+
+        let skipped: Vec<Value> = self
+            .skipped
+            .iter()
+            .map(|skip| {
+                json!({
+                    "path": skip.path().to_string_lossy(),
+                    "reason": skip.reason().to_string(),
+                })
+            })
+            .collect();
+
+        let deleted_targets: Vec<String> = self
+            .deleted_targets
+            .iter()
+            .map(|target| target.to_string_lossy().to_string())
+            .collect();
+
+        let error = self.error.as_ref().map(|error| {
+            json!({
+                "kind": error.kind,
+                "path": error.path.to_string_lossy(),
+                "message": error.message,
+            })
+        });
+
+        json!({
+            "manifest": path,
+            "action": self.action.map(|action| action.as_str()),
+            "before": self.before,
+            "after": self.after,
+            "added": self.added,
+            "removed": self.removed,
+            "kept": self.kept(),
+            "skipped": skipped,
+            "warnings": self.warnings,
+            "deleted_targets": deleted_targets,
+            "dry_run": self.dry_run,
+            "error": error,
+        })
+        .to_string()
+    }
+}
+
+impl Action {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Created => "created",
+            Action::Updated => "updated",
+            Action::Unchanged => "unchanged",
+            Action::Restored => "restored",
+        }
+    }
+}
+
+impl ReportedError {
+    fn new(error: &Error) -> ReportedError {
+        let kind = match error {
+            Error::FileAlreadyExists { .. } => "file_already_exists",
+            Error::NoBackup { .. } => "no_backup",
+            Error::NoRootPackage { .. } => "no_root_package",
+            Error::ConflictingPatch { .. } => "conflicting_patch",
+            Error::Io { operation, .. } => match operation {
+                Operation::Discover => "discover_error",
+                Operation::Read => "read_error",
+                Operation::Parse => "parse_error",
+                Operation::Write => "write_error",
+            },
+            Error::Parse { .. } => "parse_error",
+            Error::Unsearched { .. } => "unsearched",
+        };
+
+        let message = match error.source() {
+            Some(source) => format!("{}: {}", error, source),
+            None => error.to_string(),
+        };
+
+        ReportedError {
+            kind,
+            path: error.path().to_path_buf(),
+            message,
+        }
+    }
+
+    /// A snake case identifier for the kind of [`Error`], e.g. `read_error`.
+    pub fn kind(&self) -> &str {
+        self.kind
+    }
+
+    /// The file or directory the error is about.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The error followed by its cause, if it has one.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
@@ -1,23 +1,46 @@
-use std::fmt::Debug;
-use std::fs::{self, File, OpenOptions};
-use std::io::prelude::*;
-use std::io::{Error as IoError, ErrorKind, SeekFrom};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::io::{Error as IoError, ErrorKind};
 use std::ops::Deref;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
-use crate::options::FileExistsBehaviour;
-use crate::options::Options;
-use crate::toml_editor;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use toml_edit::TomlError;
+
+use crate::conflict::Conflict;
+use crate::discovery_warning::DiscoveryWarning;
+use crate::error::{Error, Operation};
+use crate::external_path_dependency::ExternalPathDependency;
+use crate::file_system::FileSystem;
+use crate::lockfile;
+use crate::manifest_change::ManifestChange;
+use crate::options::{FileExistsBehaviour, NestedWorkspaceBehaviour};
+use crate::options::{Options, Resolver};
+use crate::resolver_mismatch::ResolverMismatch;
+use crate::skipped_crate::{SkipReason, SkippedCrate};
+use crate::stale_target::StaleTarget;
+use crate::toml_changes::TomlChanges;
+use crate::toml_editor::{self, ExistingEntry, ManifestKind};
 use crate::toml_file::TomlFile;
+use crate::update_report::UpdateReport;
 
-#[derive(Debug)]
-pub enum Error {
-    FileAlreadyExists,
-    GenericCreationError(IoError),
-    ReadError(IoError),
-    ParseError,
-    WriteError(IoError),
-}
+/// Files and directories of a root package that move with it, following cargo's layout.
+const ROOT_PACKAGE_ENTRIES: [&str; 5] = ["src", "build.rs", "benches", "examples", "tests"];
+
+/// The crates found below the root, those skipped, and the paths that could not be searched.
+type Discovered = (Vec<String>, Vec<SkippedCrate>, Vec<DiscoveryWarning>);
+
+/// Members with the contents of their manifests.
+type MemberManifests<'m> = Vec<(&'m String, String)>;
+
+/// A key that members declare, each member's value for it, and either the value they agree on or
+/// the values that conflict.
+type Compared<K> = (
+    K,
+    Vec<(String, String)>,
+    Result<String, Vec<(String, String)>>,
+);
 
 pub struct Workspace {
     toml: TomlFile,
@@ -36,123 +59,1423 @@ impl Workspace {
         &self.toml
     }
 
+    /// Creates or updates the toml file, returning its path. Use
+    /// [`update_report`](Workspace::update_report) to find out what changed.
     pub fn update_toml(&self) -> Result<TomlFile, Error> {
-        self.create_path()
-            .map_err(|err| Error::GenericCreationError(err))?;
+        self.update().map(|changes| changes.toml().clone())
+    }
 
-        let mut sub_crates = self
-            .find_sub_crates()
-            .map_err(|err| Error::GenericCreationError(err))?;
+    /// Updates the toml file like [`update_toml`](Workspace::update_toml), returning the changes
+    /// that were written. A file that already has the proposed contents is not rewritten.
+    pub fn update(&self) -> Result<TomlChanges, Error> {
+        if !self.options.dry_run {
+            self.create_path()
+                .map_err(Error::io(Operation::Write, self.path()))?;
+        }
+
+        // only an update builds on the current contents, the rest just replace them
+        let exists = self.fs().exists(&self.toml);
+        let current = match self.options.existing_file_behaviour {
+            FileExistsBehaviour::Update => Some(
+                self.read_toml()
+                    .map_err(Error::io(Operation::Read, &self.toml))?,
+            ),
+            _ if exists => self.read_toml().ok(),
+            _ => None,
+        };
+
+        let changes = self.propose_changes(current)?;
+
+        if exists && !self.options.existing_file_behaviour.replaces() {
+            return Err(Error::FileAlreadyExists {
+                path: self.toml.to_path_buf(),
+            });
+        }
 
+        if self.options.dry_run {
+            return Ok(changes);
+        }
+
+        if let Some(dir) = &self.options.move_root_package {
+            self.move_root_package(dir)
+                .map_err(Error::io(Operation::Write, &self.path().join(dir)))?;
+        }
+
+        // leave an identical file alone, so its modification time does not trigger rebuilds
+        let write = changes.current() != Some(changes.proposed());
+        if write {
+            if changes.current().is_some() && self.options.backup {
+                self.fs()
+                    .copy(&self.toml, &self.backup_path())
+                    .map_err(Error::io(Operation::Write, &self.backup_path()))?;
+            }
+
+            self.fs()
+                .write(&self.toml, changes.proposed())
+                .map_err(Error::io(Operation::Write, &self.toml))?;
+        }
+
+        for change in changes.manifest_changes() {
+            self.write_manifest_change(change)
+                .map_err(Error::io(Operation::Write, change.toml()))?;
+        }
+
+        Ok(changes.with_written(write))
+    }
+
+    /// Puts back the toml file saved by the last update with
+    /// [`backup`](crate::OptionsBuilder::backup) set, consuming the backup. Member manifests and
+    /// moved files are not restored.
+    pub fn undo(&self) -> Result<TomlFile, Error> {
+        let backup = self.backup_path();
+        if !self.fs().is_file(&backup) {
+            return Err(Error::NoBackup { path: backup });
+        }
+
+        self.fs()
+            .rename(&backup, &self.toml)
+            .map_err(Error::io(Operation::Write, &self.toml))?;
+
+        Ok(self.toml.clone())
+    }
+
+    /// Works out what [`update_toml`](Workspace::update_toml) would write, without writing it.
+    ///
+    /// A missing toml file is proposed as a new file whatever the existing file behaviour.
+    pub fn check_toml(&self) -> Result<TomlChanges, Error> {
+        let current = match self.read_toml() {
+            Ok(contents) => Some(contents),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(Error::io(Operation::Read, &self.toml)(err)),
+        };
+
+        if current.is_some() && self.options.existing_file_behaviour == FileExistsBehaviour::Halt {
+            return Err(Error::FileAlreadyExists {
+                path: self.toml.to_path_buf(),
+            });
+        }
+
+        self.propose_changes(current)
+    }
+
+    /// Updates the toml file like [`update`](Workspace::update), reporting the members before and
+    /// after, what happened to the file, or why it failed.
+    pub fn update_report(&self) -> UpdateReport {
+        match self.update() {
+            Ok(changes) => UpdateReport::new(&changes, self.options.dry_run),
+            Err(err) => UpdateReport::failed(&self.toml, &err),
+        }
+    }
+
+    /// Reports what [`update_report`](Workspace::update_report) would do, without writing.
+    pub fn check_report(&self) -> UpdateReport {
+        match self.check_toml() {
+            Ok(changes) => UpdateReport::new(&changes, true),
+            Err(err) => UpdateReport::failed(&self.toml, &err),
+        }
+    }
+
+    /// Restores the toml file like [`undo`](Workspace::undo), reporting the members before and
+    /// after, or why it failed.
+    pub fn undo_report(&self) -> UpdateReport {
+        let before = self.read_toml().ok();
+
+        let restored = self
+            .undo()
+            .and_then(|toml| self.read_toml().map_err(Error::io(Operation::Read, &toml)));
+
+        match restored {
+            Ok(after) => {
+                UpdateReport::restored(&TomlChanges::new(self.toml.clone(), before, after, vec![]))
+            }
+            Err(err) => UpdateReport::failed(&self.toml, &err),
+        }
+    }
+
+    /// Deletes the [`stale_targets`](Workspace::stale_targets) after a successful update, adding
+    /// them to its report. The first directory that could not be deleted fails the report.
+    pub fn clean_targets_report(&self, report: UpdateReport) -> UpdateReport {
+        if report.error().is_some() || report.dry_run() {
+            return report;
+        }
+
+        let targets = match self.stale_targets() {
+            Ok(targets) => targets,
+            Err(err) => return report.with_deleted_targets(vec![], Some(&err)),
+        };
+
+        let mut deleted = vec![];
+        for target in targets {
+            if let Err(err) = target.remove() {
+                let err = Error::io(Operation::Write, target.path())(err);
+                return report.with_deleted_targets(deleted, Some(&err));
+            }
+            deleted.push(target.path().to_path_buf());
+        }
+
+        report.with_deleted_targets(deleted, None)
+    }
+
+    /// Lists the `target` directories that members still have from being built on their own.
+    /// The members are read from the toml file, so this follows an update.
+    pub fn stale_targets(&self) -> Result<Vec<StaleTarget>, Error> {
+        let contents = self
+            .read_toml()
+            .map_err(Error::io(Operation::Read, &self.toml))?;
+        let members = toml_editor::read_members(&contents).map_err(Error::parse(&self.toml))?;
+
+        let mut targets = vec![];
+
+        for member in members {
+            let dirs = if member.ends_with("/*") {
+                self.expand_glob(&member)
+            } else if Workspace::is_glob(&member) {
+                continue;
+            } else {
+                vec![member]
+            };
+
+            // the root's target directory is the workspace's
+            for dir in dirs
+                .iter()
+                .filter(|dir| Workspace::normalise_member(dir) != ".")
+            {
+                let target = self.path().join(dir).join("target");
+                if self.fs().is_dir(&target) {
+                    targets.push(StaleTarget::new(target, self.options.file_system.clone()));
+                }
+            }
+        }
+
+        Ok(targets)
+    }
+
+    fn propose_changes(&self, current: Option<String>) -> Result<TomlChanges, Error> {
+        let existing = match (&self.options.existing_file_behaviour, &current) {
+            (FileExistsBehaviour::Update, Some(current)) => Some(current.as_str()),
+            _ => None,
+        };
+
+        let (existing, mut manifest_changes) = match (&self.options.move_root_package, existing) {
+            (Some(dir), Some(current)) => {
+                let (root, package) = self.split_root_package(dir, current)?;
+                (Some(root), vec![package])
+            }
+            (Some(_), None) => {
+                return Err(Error::NoRootPackage {
+                    path: self.toml.to_path_buf(),
+                })
+            }
+            (None, existing) => (existing.map(String::from), vec![]),
+        };
+        let existing = existing.as_deref();
+
+        let mut excludes = self.excludes(existing)?;
+
+        let (mut sub_crates, mut skipped, discovery_warnings) =
+            self.find_sorted_sub_crates(&excludes)?;
+
+        manifest_changes.extend(self.handle_nested_workspaces(
+            &mut sub_crates,
+            &mut skipped,
+            &mut excludes,
+        )?);
+
+        // a package in the root toml file is a member of its own workspace
+        let root_is_package = existing.is_some_and(|current| {
+            matches!(
+                toml_editor::manifest_kind(current),
+                Ok(ManifestKind::Package)
+            )
+        });
+        if let Some(dir) = &self.options.move_root_package {
+            sub_crates.push(dir.clone());
+        } else if root_is_package {
+            sub_crates.push(".".to_string());
+        }
         sub_crates.sort();
+        sub_crates.dedup();
+        let members = sub_crates.clone();
+
+        if self.options.collapse_globs {
+            sub_crates = toml_editor::collapse_globs(&sub_crates, |glob| self.expand_glob(glob));
+        }
+
+        let new_file_content = match existing {
+            Some(current) => self.merge_members(current, &sub_crates),
+            None => toml_editor::toml_update("", &sub_crates),
+        }
+        .and_then(|toml| toml_editor::toml_exclude(&toml, &excludes, &self.options.sort_order))
+        .map_err(Error::parse(&self.toml))?;
+
+        let mut conflicts = vec![];
+        let mut external = vec![];
+        let new_file_content = if self.options.workspace_path_dependencies {
+            self.workspace_path_dependencies(
+                &new_file_content,
+                &members,
+                &mut manifest_changes,
+                &mut conflicts,
+                &mut external,
+            )?
+        } else {
+            new_file_content
+        };
+        let new_file_content = if self.options.hoist_dependencies {
+            self.hoist_dependencies(
+                &new_file_content,
+                &members,
+                &mut manifest_changes,
+                &mut conflicts,
+            )?
+        } else {
+            new_file_content
+        };
+        let new_file_content = if self.options.inherit_package_fields {
+            self.inherit_package_fields(
+                &new_file_content,
+                &members,
+                &mut manifest_changes,
+                &mut conflicts,
+            )?
+        } else {
+            new_file_content
+        };
+
+        let new_file_content = if self.options.consolidate_profiles {
+            self.consolidate_profiles(
+                &new_file_content,
+                &members,
+                &mut manifest_changes,
+                &mut conflicts,
+            )?
+        } else {
+            new_file_content
+        };
+
+        let new_file_content = if self.options.consolidate_patches {
+            self.consolidate_patches(&new_file_content, &members, &mut manifest_changes)?
+        } else {
+            new_file_content
+        };
+
+        if self.options.merge_lockfiles {
+            self.merge_lockfiles(&members, &mut manifest_changes)?;
+        }
 
-        let mut file = self.open_file()?;
+        let (new_file_content, resolver_mismatch) = self.choose_resolver(
+            new_file_content,
+            existing.is_some(),
+            &members,
+            &manifest_changes,
+        )?;
 
-        self.write_toml_to_file(&mut file, &sub_crates)
-            .map(|_| self.toml.clone())
+        Ok(
+            TomlChanges::new(self.toml.clone(), current, new_file_content, skipped)
+                .with_discovery_warnings(discovery_warnings)
+                .with_manifest_changes(manifest_changes)
+                .with_conflicts(conflicts)
+                .with_external_path_dependencies(external)
+                .with_resolver_mismatch(resolver_mismatch),
+        )
     }
 
-    fn path(&self) -> &PathBuf {
-        &self.options.path
+    /// Moves member profile settings into `root`, returning the new root contents. Settings that
+    /// disagree with the root or with each other are recorded as conflicts and left in the
+    /// members. The rest are removed from the members.
+    fn consolidate_profiles(
+        &self,
+        root: &str,
+        members: &[String],
+        changes: &mut Vec<ManifestChange>,
+        conflicts: &mut Vec<Conflict>,
+    ) -> Result<String, Error> {
+        let root_settings: BTreeMap<Vec<String>, String> = toml_editor::read_profiles(root)
+            .map_err(Error::parse(&self.toml))?
+            .into_iter()
+            .collect();
+
+        let (manifests, compared) =
+            self.compare_members(&root_settings, members, changes, |member, contents| {
+                toml_editor::read_profiles(contents)
+                    .map_err(Error::parse(&self.member_toml(member)))
+            })?;
+        let mut manifests: Vec<(&String, String, Vec<Vec<String>>)> = manifests
+            .into_iter()
+            .map(|(member, contents)| (member, contents, vec![]))
+            .collect();
+
+        let mut hoisted = vec![];
+
+        for (path, values, agreed) in compared {
+            let existing = root_settings.get(&path);
+
+            let conflicting = match agreed {
+                Ok(expected) => {
+                    if existing.is_none() {
+                        hoisted.push((path, expected));
+                    }
+                    continue;
+                }
+                Err(conflicting) => conflicting,
+            };
+
+            // with nothing in the root to go by, every member keeps its setting
+            let kept: Vec<&String> = values
+                .iter()
+                .filter(|(_, setting)| existing != Some(setting))
+                .map(|(member, _)| member)
+                .collect();
+            for (member, _, keep) in &mut manifests {
+                if kept.contains(member) {
+                    keep.push(path.clone());
+                }
+            }
+
+            conflicts.push(Conflict::new(
+                format!("profile.{}", path.join(".")),
+                conflicting,
+            ));
+        }
+
+        for (member, contents, keep) in manifests {
+            let proposed = toml_editor::remove_profiles(&contents, &keep)
+                .map_err(Error::parse(&self.member_toml(member)))?;
+
+            if proposed != contents {
+                self.change_member_manifest(member, contents, proposed, changes);
+            }
+        }
+
+        toml_editor::toml_profiles(root, &hoisted).map_err(Error::parse(&self.toml))
     }
 
-    fn create_path(&self) -> Result<(), IoError> {
-        fs::create_dir_all(self.path())
+    /// Moves member `[patch.*]` and `[replace]` entries into `root`, returning the new root
+    /// contents. Entries that patch the same crate differently are an error.
+    fn consolidate_patches(
+        &self,
+        root: &str,
+        members: &[String],
+        changes: &mut Vec<ManifestChange>,
+    ) -> Result<String, Error> {
+        let root_patches: BTreeMap<Vec<String>, String> =
+            toml_editor::read_patches(root, |path| path.to_string())
+                .map_err(Error::parse(&self.toml))?
+                .into_iter()
+                .collect();
+
+        let (manifests, compared) =
+            self.compare_members(&root_patches, members, changes, |member, contents| {
+                toml_editor::read_patches(contents, |path| {
+                    // leaving the root, this always resolves
+                    self.resolve_path(member, path, true)
+                        .unwrap_or_else(|| path.to_string())
+                })
+                .map_err(Error::parse(&self.member_toml(member)))
+            })?;
+
+        let mut lifted = vec![];
+
+        for (path, _, agreed) in &compared {
+            match agreed {
+                Ok(expected) if !root_patches.contains_key(path) => {
+                    lifted.push((path.clone(), expected.clone()))
+                }
+                Ok(_) => {}
+                Err(values) => {
+                    return Err(Error::ConflictingPatch {
+                        path: self.toml.to_path_buf(),
+                        conflict: Conflict::new(path.join("."), values.clone()),
+                    })
+                }
+            }
+        }
+
+        for (member, contents) in manifests {
+            // every entry read from the member is now in the root
+            let read: Vec<Vec<String>> = compared
+                .iter()
+                .filter(|(_, values, _)| values.iter().any(|(user, _)| user == member))
+                .map(|(path, _, _)| path.clone())
+                .collect();
+            let proposed = toml_editor::remove_patches(&contents, &read)
+                .map_err(Error::parse(&self.member_toml(member)))?;
+
+            if proposed != contents {
+                self.change_member_manifest(member, contents, proposed, changes);
+            }
+        }
+
+        toml_editor::toml_patches(root, &lifted).map_err(Error::parse(&self.toml))
     }
 
-    fn find_sub_crates(&self) -> Result<Vec<String>, IoError> {
-        let root = &self.options.path;
-        let sub_toml_files = Workspace::search_for_cargo_files(root, 0)?;
+    /// Proposes a workspace `Cargo.lock` that merges the members' lockfiles into any existing one,
+    /// and the deletion of the members' lockfiles.
+    fn merge_lockfiles(
+        &self,
+        members: &[String],
+        changes: &mut Vec<ManifestChange>,
+    ) -> Result<(), Error> {
+        let lock = TomlFile::new(self.path().join("Cargo.lock"));
+        let current = match self.fs().read_to_string(&lock) {
+            Ok(contents) => Some(contents),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(Error::io(Operation::Read, &lock)(err)),
+        };
 
-        Ok(sub_toml_files
-            .iter()
-            .map(|p| {
-                p.parent()
-                    .unwrap()
-                    .strip_prefix(root)
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string()
-            })
-            .collect())
+        let mut lockfiles: Vec<String> = current.iter().cloned().collect();
+        let mut deletions = vec![];
+
+        for member in members.iter().filter(|member| *member != ".") {
+            let member_lock = TomlFile::new(self.path().join(member).join("Cargo.lock"));
+
+            match self.fs().read_to_string(&member_lock) {
+                Ok(contents) => {
+                    lockfiles.push(contents.clone());
+                    deletions.push(ManifestChange::new(member_lock, contents, None));
+                }
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(Error::io(Operation::Read, &member_lock)(err)),
+            }
+        }
+
+        if deletions.is_empty() {
+            return Ok(());
+        }
+
+        let merged = lockfile::merge_lockfiles(&lockfiles).map_err(Error::parse(&lock))?;
+
+        changes.push(ManifestChange::new(
+            lock,
+            current.unwrap_or_default(),
+            Some(merged),
+        ));
+        changes.extend(deletions);
+
+        Ok(())
     }
 
-    fn search_for_cargo_files(dir: &PathBuf, depth: i32) -> Result<Vec<PathBuf>, IoError> {
-        let mut results: Vec<PathBuf> = vec![];
+    /// Sets the resolver from the options, or from the members' editions for a new file. An
+    /// existing resolver that is older than the members' editions call for is reported instead.
+    fn choose_resolver(
+        &self,
+        mut root: String,
+        updating: bool,
+        members: &[String],
+        changes: &[ManifestChange],
+    ) -> Result<(String, Option<ResolverMismatch>), Error> {
+        if let Some(resolver) = self.options.resolver {
+            return toml_editor::toml_resolver(&root, resolver.as_str())
+                .map(|root| (root, None))
+                .map_err(Error::parse(&self.toml));
+        }
 
-        // do not look in the workspace root
-        if depth > 0 {
-            for path in fs::read_dir(dir)? {
-                let path = path.unwrap().path();
-
-                if path.is_file() {
-                    match path.file_name() {
-                        Some(p) if p == "Cargo.toml" => {
-                            results.push(path);
-                            break;
-                        }
-                        _ => (),
+        let expected = self.members_resolver(&root, members, changes)?;
+
+        if !updating {
+            return match expected {
+                Resolver::V1 => Ok((root, None)),
+                _ => toml_editor::toml_resolver(&root, expected.as_str())
+                    .map(|root| (root, None))
+                    .map_err(Error::parse(&self.toml)),
+            };
+        }
+
+        let current = match toml_editor::read_resolver(&root).map_err(Error::parse(&self.toml))? {
+            Some(resolver) => Resolver::parse(&resolver),
+            // the virtual manifest a moved root package leaves behind keeps the resolver the
+            // package's edition picked
+            None => match &self.options.move_root_package {
+                Some(dir) => {
+                    let package = self.member_manifest(dir, changes)?;
+                    let resolver = self
+                        .edition_resolver(&self.member_toml(dir), &package, &root)?
+                        .unwrap_or(Resolver::V1);
+                    if resolver != Resolver::V1 {
+                        root = toml_editor::toml_resolver(&root, resolver.as_str())
+                            .map_err(Error::parse(&self.toml))?;
                     }
+                    Some(resolver)
+                }
+                // a root package's edition picks the resolver, otherwise it is the oldest
+                None => Some(
+                    self.edition_resolver(&self.toml, &root, &root)?
+                        .unwrap_or(Resolver::V1),
+                ),
+            },
+        };
+
+        let mismatch = match current {
+            Some(current) if current < expected => Some(ResolverMismatch::new(current, expected)),
+            _ => None,
+        };
+
+        Ok((root, mismatch))
+    }
+
+    /// The resolver the newest edition among the members defaults to.
+    fn members_resolver(
+        &self,
+        root: &str,
+        members: &[String],
+        changes: &[ManifestChange],
+    ) -> Result<Resolver, Error> {
+        let mut resolver = Resolver::V1;
+
+        for member in members {
+            let contents = match member.as_str() {
+                "." => root.to_string(),
+                member => self.member_manifest(member, changes)?,
+            };
+
+            if let Some(edition) =
+                self.edition_resolver(&self.member_toml(member), &contents, root)?
+            {
+                if edition > resolver {
+                    resolver = edition;
+                }
+            }
+        }
+
+        Ok(resolver)
+    }
+
+    /// The resolver the edition of a package defaults to, looking an inherited edition up in
+    /// `root`. `None` if there is no package. `toml` is where `contents` came from.
+    fn edition_resolver(
+        &self,
+        toml: &Path,
+        contents: &str,
+        root: &str,
+    ) -> Result<Option<Resolver>, Error> {
+        if !toml_editor::has_package(contents).map_err(Error::parse(toml))? {
+            return Ok(None);
+        }
+
+        let edition = toml_editor::read_package_fields(contents)
+            .map_err(Error::parse(toml))?
+            .into_iter()
+            .find(|(field, _)| field == "edition")
+            .map(|(_, edition)| edition);
+
+        let edition = match edition {
+            Some(Some(edition)) => Some(edition),
+            Some(None) => toml_editor::read_workspace_package(root)
+                .map_err(Error::parse(&self.toml))?
+                .into_iter()
+                .find(|(field, _)| field == "edition")
+                .map(|(_, edition)| edition),
+            None => None,
+        };
+
+        Ok(Some(Resolver::for_edition(
+            edition.as_deref().unwrap_or("\"2015\"").trim_matches('"'),
+        )))
+    }
+
+    /// Moves dependencies that members agree on into `[workspace.dependencies]` of `root`,
+    /// returning the new root contents. Members are rewritten to inherit them, and dependencies
+    /// that members disagree on are recorded as conflicts.
+    fn hoist_dependencies(
+        &self,
+        root: &str,
+        members: &[String],
+        changes: &mut Vec<ManifestChange>,
+        conflicts: &mut Vec<Conflict>,
+    ) -> Result<String, Error> {
+        let workspace_dependencies: BTreeMap<String, String> =
+            toml_editor::read_workspace_dependencies(root)
+                .map_err(Error::parse(&self.toml))?
+                .into_iter()
+                .collect();
+
+        let (manifests, compared) = self.compare_members(
+            &workspace_dependencies,
+            members,
+            changes,
+            |member, contents| {
+                toml_editor::read_dependencies(contents)
+                    .map_err(Error::parse(&self.member_toml(member)))
+            },
+        )?;
+
+        let mut hoisted = vec![];
+        let mut inherited = vec![];
+
+        for (name, requirements, agreed) in compared {
+            let existing = workspace_dependencies.get(&name);
+
+            // a path is relative to its member, so only a registry dependency is hoisted, though
+            // a path dependency of the same name conflicts with it
+            let mut users: Vec<&String> = requirements
+                .iter()
+                .filter(|(_, requirement)| !toml_editor::is_path_requirement(requirement))
+                .map(|(member, _)| member)
+                .collect();
+            users.dedup();
+            if users.is_empty() || (existing.is_none() && users.len() < 2) {
+                continue;
+            }
+
+            let expected = match agreed {
+                Ok(expected) => expected,
+                Err(values) => {
+                    conflicts.push(Conflict::new(format!("dependencies.{}", name), values));
+                    continue;
                 }
+            };
+
+            if existing.is_none() {
+                hoisted.push((name.clone(), expected.clone()));
             }
+            inherited.push((name, expected));
         }
 
-        // do not look in sub directories after found a Cargo.toml
-        if results.is_empty() {
-            for path in fs::read_dir(dir)? {
-                let path = path.unwrap().path();
+        for (member, contents) in manifests {
+            let proposed = toml_editor::inherit_dependencies(&contents, &inherited)
+                .map_err(Error::parse(&self.member_toml(member)))?;
+
+            if proposed != contents {
+                self.change_member_manifest(member, contents, proposed, changes);
+            }
+        }
 
-                if path.is_dir() {
-                    match path.file_name() {
-                        Some(p) if p != "target" => {
-                            results.extend(Workspace::search_for_cargo_files(&path, depth + 1)?);
+        toml_editor::toml_workspace_dependencies(root, &hoisted).map_err(Error::parse(&self.toml))
+    }
+
+    /// Moves path dependencies on other members into `[workspace.dependencies]` of `root`, with
+    /// the member's version, returning the new root contents. Members are rewritten to inherit
+    /// them. Names that lead to different members are recorded as conflicts, and paths outside
+    /// the workspace root are recorded in `external`.
+    fn workspace_path_dependencies(
+        &self,
+        root: &str,
+        members: &[String],
+        changes: &mut Vec<ManifestChange>,
+        conflicts: &mut Vec<Conflict>,
+        external: &mut Vec<ExternalPathDependency>,
+    ) -> Result<String, Error> {
+        let workspace_dependencies: BTreeMap<String, String> =
+            toml_editor::read_workspace_path_dependencies(root)
+                .map_err(Error::parse(&self.toml))?
+                .into_iter()
+                .map(|(name, path)| (name, Workspace::normalise_member(&path)))
+                .collect();
+
+        let mut paths = vec![];
+        let (manifests, compared) = self.compare_members(
+            &workspace_dependencies,
+            members,
+            changes,
+            |member, contents| {
+                let mut targets = vec![];
+                for (name, path) in toml_editor::read_path_dependencies(contents)
+                    .map_err(Error::parse(&self.member_toml(member)))?
+                {
+                    match self.resolve_path(member, &path, false) {
+                        Some(target) if members.contains(&target) => {
+                            paths.push((member.to_string(), name.clone(), path));
+                            targets.push((name, target));
                         }
-                        _ => (),
+                        Some(_) => {} // inside the workspace root but not a member
+                        None => external.push(ExternalPathDependency::new(
+                            member.to_string(),
+                            name,
+                            path,
+                        )),
                     }
                 }
+                Ok(targets)
+            },
+        )?;
+        let mut hoisted = vec![];
+        let mut inherited = vec![];
+
+        for (name, _, agreed) in compared {
+            let expected = match agreed {
+                Ok(expected) => expected,
+                Err(values) => {
+                    conflicts.push(Conflict::new(format!("dependencies.{}", name), values));
+                    continue;
+                }
+            };
+
+            if !workspace_dependencies.contains_key(&name) {
+                let target = self.member_manifest(&expected, changes)?;
+                let version = toml_editor::read_package_version(&target)
+                    .map_err(Error::parse(&self.member_toml(&expected)))?;
+                hoisted.push((
+                    name.clone(),
+                    toml_editor::path_requirement(&expected, version.as_deref()),
+                ));
             }
+
+            inherited.push(name);
+        }
+
+        for (member, contents) in manifests {
+            // only the entries that led to the member compared are inherited
+            let entries: Vec<(String, String)> = paths
+                .iter()
+                .filter(|(user, name, _)| user == member && inherited.contains(name))
+                .map(|(_, name, path)| (name.clone(), path.clone()))
+                .collect();
+
+            let proposed = toml_editor::inherit_path_dependencies(&contents, &entries)
+                .map_err(Error::parse(&self.member_toml(member)))?;
+
+            if proposed != contents {
+                self.change_member_manifest(member, contents, proposed, changes);
+            }
+        }
+
+        toml_editor::toml_workspace_dependencies(root, &hoisted).map_err(Error::parse(&self.toml))
+    }
+
+    /// Resolves a path relative to a member to a `/` separated path relative to the workspace
+    /// root. A path outside the root is `None`, unless `leave_root` allows it, when an absolute
+    /// path is kept as it is and a relative one starts with `..`.
+    fn resolve_path(&self, member: &str, path: &str, leave_root: bool) -> Option<String> {
+        let path = Path::new(path);
+
+        if path.is_absolute() {
+            if leave_root {
+                return Some(path.to_string_lossy().to_string());
+            }
+
+            let root = self.fs().canonicalize(self.path()).ok()?;
+            let path = self.fs().canonicalize(path).ok()?;
+            let relative = path.strip_prefix(root).ok()?;
+
+            return Some(Workspace::normalise_member(
+                &relative.to_string_lossy().replace('\\', "/"),
+            ));
         }
 
-        Ok(results)
+        let mut resolved: Vec<String> = member
+            .split('/')
+            .filter(|c| !c.is_empty() && *c != ".")
+            .map(String::from)
+            .collect();
+
+        for component in path.components() {
+            match component {
+                Component::ParentDir if resolved.last().is_some_and(|c| c != "..") => {
+                    resolved.pop();
+                }
+                Component::ParentDir if leave_root => resolved.push("..".to_string()),
+                Component::ParentDir => return None,
+                Component::Normal(name) => resolved.push(name.to_string_lossy().to_string()),
+                _ => {}
+            }
+        }
+
+        Some(Workspace::normalise_member(&resolved.join("/")))
     }
 
-    fn open_file(&self) -> Result<File, Error> {
-        OpenOptions::new()
-            .write(true)
-            .read(self.options.existing_file_behaviour == FileExistsBehaviour::Update)
-            .create_new(self.options.existing_file_behaviour.create_new())
-            .create(self.options.existing_file_behaviour != FileExistsBehaviour::Update)
-            .open(self.toml.deref())
-            .map_err(|err| match err.kind() {
-                ErrorKind::AlreadyExists => Error::FileAlreadyExists,
-                _ => Error::GenericCreationError(err),
+    /// Reads the manifest of each member apart from a root package, with the keys and values
+    /// `read` finds in it. Each key's values are compared with the workspace's value in
+    /// `root_values`, or with the first member's if the workspace has none. Conflicting values
+    /// start with the workspace's.
+    fn compare_members<'m, K, F>(
+        &self,
+        root_values: &BTreeMap<K, String>,
+        members: &'m [String],
+        changes: &[ManifestChange],
+        mut read: F,
+    ) -> Result<(MemberManifests<'m>, Vec<Compared<K>>), Error>
+    where
+        K: Ord,
+        F: FnMut(&str, &str) -> Result<Vec<(K, String)>, Error>,
+    {
+        let mut manifests = vec![];
+        let mut declared: BTreeMap<K, Vec<(String, String)>> = BTreeMap::new();
+
+        for member in members.iter().filter(|member| *member != ".") {
+            let contents = self.member_manifest(member, changes)?;
+
+            for (key, value) in read(member, &contents)? {
+                declared
+                    .entry(key)
+                    .or_default()
+                    .push((member.clone(), value));
+            }
+
+            manifests.push((member, contents));
+        }
+
+        let compared = declared
+            .into_iter()
+            .map(|(key, values)| {
+                let existing = root_values.get(&key);
+                let expected = existing.unwrap_or(&values[0].1).clone();
+
+                let agreed = if values.iter().all(|(_, value)| *value == expected) {
+                    Ok(expected)
+                } else {
+                    let mut conflicting: Vec<(String, String)> = existing
+                        .map(|value| ("workspace".to_string(), value.clone()))
+                        .into_iter()
+                        .collect();
+                    conflicting.extend(values.iter().cloned());
+                    Err(conflicting)
+                };
+
+                (key, values, agreed)
             })
+            .collect();
+
+        Ok((manifests, compared))
+    }
+
+    /// Moves `[package]` fields that every member declares the same into `[workspace.package]`
+    /// of `root`, returning the new root contents. Members are rewritten to inherit them, and
+    /// fields that members disagree on are recorded as conflicts.
+    fn inherit_package_fields(
+        &self,
+        root: &str,
+        members: &[String],
+        changes: &mut Vec<ManifestChange>,
+        conflicts: &mut Vec<Conflict>,
+    ) -> Result<String, Error> {
+        let workspace_fields: BTreeMap<String, String> = toml_editor::read_workspace_package(root)
+            .map_err(Error::parse(&self.toml))?
+            .into_iter()
+            .collect();
+
+        let mut manifests = vec![];
+        for member in members.iter().filter(|member| *member != ".") {
+            let contents = self.member_manifest(member, changes)?;
+            let fields: BTreeMap<String, Option<String>> =
+                toml_editor::read_package_fields(&contents)
+                    .map_err(Error::parse(&self.member_toml(member)))?
+                    .into_iter()
+                    .collect();
+
+            manifests.push((member, contents, fields, vec![]));
+        }
+
+        let mut hoisted = vec![];
+
+        for field in &toml_editor::INHERITABLE_PACKAGE_FIELDS {
+            let declared: Vec<(String, String)> = manifests
+                .iter()
+                .filter_map(|(member, _, fields, _)| match fields.get(*field) {
+                    Some(Some(value)) => Some((member.to_string(), value.clone())),
+                    _ => None,
+                })
+                .collect();
+
+            let expected = match workspace_fields.get(*field) {
+                Some(expected) => expected.clone(),
+                None if declared.len() >= 2
+                    && declared.len() == manifests.len()
+                    && declared.iter().all(|(_, value)| *value == declared[0].1) =>
+                {
+                    hoisted.push((field.to_string(), declared[0].1.clone()));
+                    declared[0].1.clone()
+                }
+                None => {
+                    if declared.len() >= 2 {
+                        let mut values = declared;
+                        for (member, _, fields, _) in &manifests {
+                            if !fields.contains_key(*field) {
+                                values.push((member.to_string(), "no value".to_string()));
+                            }
+                        }
+                        conflicts.push(Conflict::new(format!("package.{}", field), values));
+                    }
+                    continue;
+                }
+            };
+
+            let disagreeing: Vec<(String, String)> = declared
+                .iter()
+                .filter(|(_, value)| *value != expected)
+                .cloned()
+                .collect();
+            if !disagreeing.is_empty() {
+                let mut values = vec![("workspace".to_string(), expected.clone())];
+                values.extend(disagreeing);
+                conflicts.push(Conflict::new(format!("package.{}", field), values));
+            }
+
+            for (_, _, fields, inherited) in &mut manifests {
+                if fields.get(*field) == Some(&Some(expected.clone())) {
+                    inherited.push(field.to_string());
+                }
+            }
+        }
+
+        for (member, contents, _, inherited) in manifests {
+            let proposed = toml_editor::inherit_package_fields(&contents, &inherited)
+                .map_err(Error::parse(&self.member_toml(member)))?;
+
+            if proposed != contents {
+                self.change_member_manifest(member, contents, proposed, changes);
+            }
+        }
+
+        toml_editor::toml_workspace_package(root, &hoisted).map_err(Error::parse(&self.toml))
+    }
+
+    /// The contents of a member's manifest, taking any change already proposed for it into
+    /// account.
+    fn member_manifest(&self, member: &str, changes: &[ManifestChange]) -> Result<String, Error> {
+        let toml = self.member_toml(member);
+
+        match changes.iter().find(|change| *change.toml().deref() == toml) {
+            Some(change) => Ok(change.proposed().unwrap_or_default().to_string()),
+            None => self
+                .fs()
+                .read_to_string(&toml)
+                .map_err(Error::io(Operation::Read, &toml)),
+        }
+    }
+
+    /// The path of a member's manifest, which for `.` is the toml file itself.
+    fn member_toml(&self, member: &str) -> PathBuf {
+        match member {
+            "." => self.toml.to_path_buf(),
+            member => self.path().join(member).join("Cargo.toml"),
+        }
+    }
+
+    /// Records a new proposal for a member's manifest, replacing any change already proposed.
+    fn change_member_manifest(
+        &self,
+        member: &str,
+        contents: String,
+        proposed: String,
+        changes: &mut Vec<ManifestChange>,
+    ) {
+        let toml = TomlFile::new(self.member_toml(member));
+
+        match changes
+            .iter_mut()
+            .find(|change| change.toml().deref() == toml.deref())
+        {
+            Some(change) => {
+                *change = ManifestChange::new(toml, change.current().to_string(), Some(proposed))
+            }
+            None => changes.push(ManifestChange::new(toml, contents, Some(proposed))),
+        }
+    }
+
+    /// Splits the root toml file into the virtual manifest that stays at the root and the change
+    /// that writes the package manifest into `dir`.
+    fn split_root_package(
+        &self,
+        dir: &str,
+        current: &str,
+    ) -> Result<(String, ManifestChange), Error> {
+        let destination = self.path().join(dir);
+        let moved = |entry: &str| {
+            ROOT_PACKAGE_ENTRIES.contains(&entry) && self.fs().exists(&self.path().join(entry))
+        };
+
+        if self.fs().exists(&destination.join("Cargo.toml"))
+            || ROOT_PACKAGE_ENTRIES
+                .iter()
+                .any(|entry| moved(entry) && self.fs().exists(&destination.join(entry)))
+        {
+            return Err(Error::FileAlreadyExists { path: destination });
+        }
+
+        let (root, package) =
+            toml_editor::split_root_package(current, dir.split('/').count(), moved)
+                .map_err(Error::parse(&self.toml))?
+                .ok_or_else(|| Error::NoRootPackage {
+                    path: self.toml.to_path_buf(),
+                })?;
+
+        let toml = TomlFile::new(destination.join("Cargo.toml"));
+
+        Ok((
+            root,
+            ManifestChange::new(toml, String::new(), Some(package)),
+        ))
+    }
+
+    fn move_root_package(&self, dir: &str) -> Result<(), IoError> {
+        let destination = self.path().join(dir);
+        self.fs().create_dir_all(&destination)?;
+
+        for entry in &ROOT_PACKAGE_ENTRIES {
+            let source = self.path().join(entry);
+            if self.fs().exists(&source) {
+                self.fs().rename(&source, &destination.join(entry))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Absorbs or excludes the nested workspaces that discovery skipped, depending on the
+    /// options, returning the changes needed to their manifests.
+    fn handle_nested_workspaces(
+        &self,
+        sub_crates: &mut Vec<String>,
+        skipped: &mut Vec<SkippedCrate>,
+        excludes: &mut Vec<String>,
+    ) -> Result<Vec<ManifestChange>, Error> {
+        let behaviour = &self.options.nested_workspace_behaviour;
+        if *behaviour == NestedWorkspaceBehaviour::Skip {
+            return Ok(vec![]);
+        }
+
+        let (nested, others): (Vec<SkippedCrate>, Vec<SkippedCrate>) = skipped
+            .drain(..)
+            .partition(|skip| *skip.reason() == SkipReason::NestedWorkspace);
+        *skipped = others;
+
+        let mut changes = vec![];
+
+        for workspace in nested {
+            // discovery skips paths that are not UTF-8 before reading their manifests
+            let dir = match Workspace::member_entry(workspace.path()) {
+                Some(dir) => dir,
+                None => continue,
+            };
+
+            if *behaviour == NestedWorkspaceBehaviour::Exclude {
+                excludes.push(dir);
+                continue;
+            }
+
+            let toml = TomlFile::new(self.path().join(&dir).join("Cargo.toml"));
+            let contents = self
+                .fs()
+                .read_to_string(&toml)
+                .map_err(Error::io(Operation::Read, &toml))?;
+
+            let settings =
+                toml_editor::nested_workspace_settings(&contents).map_err(Error::parse(&toml))?;
+            if !settings.is_empty() {
+                excludes.push(dir);
+                skipped.push(SkippedCrate::new(
+                    workspace.path().to_path_buf(),
+                    SkipReason::NestedWorkspaceSettings(settings),
+                ));
+                continue;
+            }
+
+            let members = toml_editor::read_members(&contents).map_err(Error::parse(&toml))?;
+            sub_crates.extend(
+                members
+                    .iter()
+                    .map(|member| member.trim_start_matches("./").trim_end_matches('/'))
+                    .filter(|member| !member.is_empty() && *member != ".")
+                    .map(|member| format!("{}/{}", dir, member)),
+            );
+
+            let proposed = toml_editor::remove_workspace(&contents).map_err(Error::parse(&toml))?;
+            if let Ok(ManifestKind::Package) = toml_editor::manifest_kind(&proposed) {
+                sub_crates.push(dir);
+            }
+
+            // a virtual manifest has nothing left once its workspace is gone
+            let proposed = if proposed.trim().is_empty() {
+                None
+            } else {
+                Some(proposed)
+            };
+
+            changes.push(ManifestChange::new(toml, contents, proposed));
+        }
+
+        excludes.sort();
+        excludes.dedup();
+        sub_crates.sort();
+        sub_crates.dedup();
+
+        Ok(changes)
+    }
+
+    /// The sorted excludes from the existing file, if any, and the options.
+    fn excludes(&self, existing: Option<&str>) -> Result<Vec<String>, Error> {
+        let mut excludes = match existing {
+            Some(current) => {
+                toml_editor::read_excludes(current).map_err(Error::parse(&self.toml))?
+            }
+            None => vec![],
+        };
+
+        for exclude in &self.options.excludes {
+            if !excludes
+                .iter()
+                .any(|e| Workspace::normalise_member(e) == Workspace::normalise_member(exclude))
+            {
+                excludes.push(exclude.clone());
+            }
+        }
+
+        excludes.sort();
+
+        Ok(excludes)
+    }
+
+    /// Like [`find_sub_crates`](Workspace::find_sub_crates), with the crates sorted. Paths that
+    /// could not be searched are an error when strict.
+    fn find_sorted_sub_crates(&self, excludes: &[String]) -> Result<Discovered, Error> {
+        let excludes = Workspace::glob_set(
+            excludes
+                .iter()
+                .map(|e| e.trim_start_matches("./").trim_end_matches('/')),
+        );
+
+        let (mut sub_crates, skipped, warnings) = self
+            .find_sub_crates(&excludes)
+            .map_err(Error::io(Operation::Discover, self.path()))?;
+
+        if self.options.strict && !warnings.is_empty() {
+            return Err(Error::Unsearched {
+                path: self.path().to_path_buf(),
+                warnings,
+            });
+        }
+
+        sub_crates.sort();
+
+        Ok((sub_crates, skipped, warnings))
+    }
+
+    fn merge_members(&self, current: &str, sub_crates: &[String]) -> Result<String, TomlError> {
+        let existing = toml_editor::read_members(current)?;
+
+        // crates already matched by a glob member need no entry of their own
+        let globs = Workspace::glob_set(
+            existing
+                .iter()
+                .map(String::as_str)
+                .filter(|m| Workspace::is_glob(m)),
+        );
+        // nor do crates listed in another form, like `./a` or `a/`
+        let listed: Vec<String> = existing
+            .iter()
+            .map(|m| Workspace::normalise_member(m))
+            .collect();
+        let sub_crates: Vec<String> = sub_crates
+            .iter()
+            .filter(|c| !globs.is_match(c.as_str()) && !listed.contains(c))
+            .cloned()
+            .collect();
+
+        // explicit members under a newly collapsed glob are no longer needed
+        let new_globs = Workspace::glob_set(
+            sub_crates
+                .iter()
+                .map(String::as_str)
+                .filter(|c| Workspace::is_glob(c)),
+        );
+
+        toml_editor::toml_merge(
+            current,
+            &sub_crates,
+            |member| {
+                let member = &Workspace::normalise_member(member);
+
+                if Workspace::is_glob(member) {
+                    ExistingEntry::Found
+                } else if new_globs.is_match(member)
+                    || (member == "." && self.options.move_root_package.is_some())
+                {
+                    ExistingEntry::Covered
+                } else if !self
+                    .fs()
+                    .is_file(&self.path().join(member).join("Cargo.toml"))
+                {
+                    ExistingEntry::Missing
+                } else {
+                    ExistingEntry::Found
+                }
+            },
+            &self.options.missing_member_behaviour,
+            &self.options.sort_order,
+        )
+    }
+
+    /// Lists everything a `<dir>/*` member glob matches, the way cargo expands it.
+    fn expand_glob(&self, glob: &str) -> Vec<String> {
+        let dir = glob.trim_end_matches("/*");
+
+        match self.fs().read_dir(&self.path().join(dir)) {
+            Ok(entries) => entries
+                .iter()
+                .filter_map(|entry| entry.file_name())
+                .map(|name| format!("{}/{}", dir, name.to_string_lossy()))
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn glob_set<'a>(patterns: impl Iterator<Item = &'a str>) -> GlobSet {
+        let mut builder = GlobSetBuilder::new();
+
+        for pattern in patterns {
+            if let Ok(glob) = GlobBuilder::new(pattern).literal_separator(true).build() {
+                builder.add(glob);
+            }
+        }
+
+        builder.build().unwrap_or_else(|_| GlobSet::empty())
+    }
+
+    fn is_glob(member: &str) -> bool {
+        member.contains(&['*', '?', '['][..])
+    }
+
+    fn normalise_member(path: &str) -> String {
+        match path.trim_start_matches("./").trim_end_matches('/') {
+            "" => ".".to_string(),
+            path => path.to_string(),
+        }
+    }
+
+    fn path(&self) -> &PathBuf {
+        &self.options.path
+    }
+
+    fn fs(&self) -> &dyn FileSystem {
+        self.options.file_system.as_ref()
+    }
+
+    fn create_path(&self) -> Result<(), IoError> {
+        self.fs().create_dir_all(self.path())
     }
 
-    fn write_toml_to_file(&self, file: &mut File, sub_crates: &[String]) -> Result<(), Error> {
-        let toml_content = match self.options.existing_file_behaviour {
-            FileExistsBehaviour::Update => {
-                Workspace::read_toml(file).map_err(|err| Error::ReadError(err))?
+    fn find_sub_crates(&self, excludes: &GlobSet) -> Result<Discovered, IoError> {
+        let (sub_toml_files, warnings) = self.search_for_cargo_files(excludes)?;
+
+        let mut sub_crates = vec![];
+        let mut skipped = vec![];
+
+        for toml in sub_toml_files {
+            let dir = match toml.parent() {
+                Some(dir) => self.relative(dir),
+                None => continue,
+            };
+
+            let member = match Workspace::member_entry(dir) {
+                Some(member) => member,
+                None => {
+                    skipped.push(SkippedCrate::new(
+                        dir.to_path_buf(),
+                        SkipReason::NonUtf8Path,
+                    ));
+                    continue;
+                }
+            };
+
+            match self.check_manifest(&toml) {
+                None => sub_crates.push(member),
+                Some(reason) => skipped.push(SkippedCrate::new(dir.to_path_buf(), reason)),
             }
-            _ => "".to_string(),
+        }
+
+        Ok((sub_crates, skipped, warnings))
+    }
+
+    /// Only manifests with a `[package]` table, and no `[workspace]` table, are members.
+    fn check_manifest(&self, toml: &Path) -> Option<SkipReason> {
+        let contents = match self.fs().read_to_string(toml) {
+            Ok(contents) => contents,
+            Err(err) => return Some(SkipReason::Unreadable(err.to_string())),
         };
 
-        let new_file_content = toml_editor::toml_update(toml_content.as_str(), &sub_crates[..])
-            .map_err(|_| Error::ParseError)?;
+        if contents.trim().is_empty() {
+            return Some(SkipReason::Empty);
+        }
+
+        match toml_editor::manifest_kind(&contents) {
+            Ok(ManifestKind::Package) => None,
+            Ok(ManifestKind::Workspace) => Some(SkipReason::NestedWorkspace),
+            Ok(ManifestKind::Other) => Some(SkipReason::NoPackage),
+            Err(err) => Some(SkipReason::Unparsable(err.to_string())),
+        }
+    }
+
+    /// The `Cargo.toml` files below the root, and the paths that could not be searched.
+    fn search_for_cargo_files(
+        &self,
+        excludes: &GlobSet,
+    ) -> Result<(Vec<PathBuf>, Vec<DiscoveryWarning>), IoError> {
+        let root = self.path().clone();
+        let excludes = excludes.clone();
+        let file_system = Arc::clone(&self.options.file_system);
+        let max_depth = self.options.max_depth.unwrap_or(usize::MAX);
+
+        let (files, errors) = self.fs().walk(
+            self.path(),
+            self.options.respect_ignore_files,
+            self.options.follow_symlinks,
+            Arc::new(move |dir| {
+                let relative = dir.strip_prefix(&root).unwrap_or(dir);
+                let depth = relative.components().count();
+                let has_cargo_file = |dir: &Path| {
+                    dir.parent()
+                        .is_some_and(|parent| file_system.is_file(&parent.join("Cargo.toml")))
+                };
+
+                // do not look in target or excluded directories, in sub directories after found
+                // a Cargo.toml, or deeper than asked
+                depth == 0
+                    || (depth <= max_depth
+                        && dir.file_name() != Some(OsStr::new("target"))
+                        && !excludes.is_match(relative)
+                        && (depth < 2 || !has_cargo_file(dir)))
+            }),
+        )?;
+
+        // do not look in the workspace root
+        let files = files
+            .into_iter()
+            .filter(|file| {
+                file.file_name().is_some_and(|name| name == "Cargo.toml")
+                    && file
+                        .parent()
+                        .is_some_and(|dir| self.relative(dir).components().count() > 0)
+            })
+            .collect();
+
+        let warnings = errors
+            .into_iter()
+            .map(|(path, err)| {
+                DiscoveryWarning::new(self.relative(&path).to_path_buf(), err.to_string())
+            })
+            .collect();
+
+        Ok((files, warnings))
+    }
+
+    /// How a directory relative to the root is written in the toml file: its components joined
+    /// with `/` on every platform. `None` if the path is not valid UTF-8.
+    fn member_entry(dir: &Path) -> Option<String> {
+        let components: Option<Vec<&str>> = dir
+            .components()
+            .map(|component| component.as_os_str().to_str())
+            .collect();
 
-        Workspace::write_toml(file, new_file_content).map_err(|err| Error::WriteError(err))
+        components.map(|components| components.join("/"))
     }
 
-    fn write_toml(file: &mut File, toml: String) -> Result<(), IoError> {
-        file.set_len(0)?; // in lieu of OpenOptions::truncate which would prevent reading
-        file.seek(SeekFrom::Start(0))?;
-        file.write_all(toml.as_bytes())
+    /// The path relative to the workspace root, or as it is if it is not below the root.
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(self.path()).unwrap_or(path)
+    }
+
+    fn backup_path(&self) -> PathBuf {
+        self.path().join("Cargo.toml.bak")
+    }
+
+    fn write_manifest_change(&self, change: &ManifestChange) -> Result<(), IoError> {
+        match change.proposed() {
+            Some(contents) => self.fs().write(change.toml(), contents),
+            None => self.fs().remove_file(change.toml()),
+        }
     }
 
-    fn read_toml(file: &mut File) -> Result<String, IoError> {
-        let mut toml = String::new();
-        file.read_to_string(&mut toml)?;
-        Ok(toml)
+    fn read_toml(&self) -> Result<String, IoError> {
+        self.fs().read_to_string(&self.toml)
     }
 }
@@ -0,0 +1,83 @@
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::process::{Command, Output};
+
+    use crate::test_utils::*;
+
+    fn wsinit(path: &Path, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_cargo-wsinit"))
+            .args(args)
+            .arg("-p")
+            .arg(path)
+            .output()
+            .expect("Expect the binary to run")
+    }
+
+    fn add_crate(root: &Path, dir: &str) {
+        let dir = root.join(dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]\nname = \"lib\"\n").unwrap();
+    }
+
+    #[test]
+    fn an_error_exits_with_a_failure() {
+        let test_root = ThreadTestPath::new_removed();
+        fs::create_dir(&*test_root).unwrap();
+        fs::write(test_root.join("Cargo.toml"), "[workspace]\n").unwrap();
+
+        let output = wsinit(&test_root, &[]);
+
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn an_unreadable_directory_exits_with_a_failure_when_strict() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let test_root = ThreadTestPath::new_removed();
+        add_crate(&test_root, "lib1");
+        add_crate(&test_root, "locked/lib2");
+        let locked = test_root.join("locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+
+        // permissions do not stop root from reading the directory
+        if fs::read_dir(&locked).is_ok() {
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+            return;
+        }
+
+        let strict = wsinit(&test_root, &["--strict"]);
+        let output = wsinit(&test_root, &[]);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(!strict.status.success());
+        assert!(String::from_utf8_lossy(&strict.stderr).contains("could not search all of"));
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Could not search"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_symbolic_link_loop_exits_with_a_failure_when_strict() {
+        let test_root = ThreadTestPath::new_removed();
+        add_crate(&test_root, "lib1");
+        std::os::unix::fs::symlink(".", test_root.join("loop")).unwrap();
+
+        let strict = wsinit(&test_root, &["--strict"]);
+
+        assert!(!strict.status.success());
+        assert!(String::from_utf8_lossy(&strict.stderr).contains("could not search all of"));
+        assert!(!test_root.join("Cargo.toml").exists());
+
+        let output = wsinit(&test_root, &[]);
+
+        assert!(output.status.success());
+        assert!(test_root.join("Cargo.toml").exists());
+    }
+}
@@ -15,6 +15,7 @@ pub fn overwrite_file(toml_file: &TomlFile, contents: &str) {
         .unwrap();
 }
 
+#[allow(dead_code)] // Used by one test crate but not another, causing a warning
 pub fn read_file(toml_file: &TomlFile) -> String {
     let mut file_contents = String::new();
     File::open(&toml_file.as_path())
@@ -0,0 +1,112 @@
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use cargo_wsinit::*;
+
+    use crate::test_utils::*;
+
+    #[test]
+    fn check_missing_toml_is_stale_and_does_not_create_it() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+        make_lib(&test_root, "lib1");
+
+        let options = Options::new(root_path, FileExistsBehaviour::Update);
+        let workspace = Workspace::new(options);
+        let changes = workspace
+            .check_toml()
+            .expect("Expect check to succeed without a file");
+
+        assert!(changes.is_stale());
+        assert_eq!(None, changes.current());
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n]\n",
+            changes.proposed()
+        );
+        assert_eq!(vec!["lib1".to_string()], changes.added());
+        assert!(!workspace.toml().exists());
+    }
+
+    #[test]
+    fn check_up_to_date_toml_is_not_stale() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+        make_lib(&test_root, "lib1");
+
+        let options = Options::new(root_path, FileExistsBehaviour::Halt);
+        Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+
+        let options = Options::new(root_path, FileExistsBehaviour::Update);
+        let changes = Workspace::new(options)
+            .check_toml()
+            .expect("Expect check to succeed");
+
+        assert!(!changes.is_stale());
+        assert!(changes.added().is_empty());
+        assert!(changes.removed().is_empty());
+        assert_eq!("", changes.diff());
+    }
+
+    #[test]
+    fn check_reports_new_crate_without_writing() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+        make_lib(&test_root, "lib1");
+
+        let options = Options::new(root_path, FileExistsBehaviour::Halt);
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+        let original_contents = read_file(&toml_file);
+
+        make_lib(&test_root, "lib2");
+
+        let options = Options::new(root_path, FileExistsBehaviour::Update);
+        let changes = Workspace::new(options)
+            .check_toml()
+            .expect("Expect check to succeed");
+
+        assert!(changes.is_stale());
+        assert_eq!(vec!["lib2".to_string()], changes.added());
+        assert!(changes.diff().contains("+    \"lib2\",\n"));
+        assert_eq!(original_contents, read_file(&toml_file));
+    }
+
+    #[test]
+    fn check_existing_toml_in_halt_mode_is_not_allowed() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        let options = Options::new(root_path, FileExistsBehaviour::Halt);
+        Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+
+        let options = Options::new(root_path, FileExistsBehaviour::Halt);
+        let error = Workspace::new(options)
+            .check_toml()
+            .expect_err("Expect check to refuse an existing file");
+
+        match error {
+            Error::FileAlreadyExists { .. } => {}
+            _ => panic!("Wrong error enum value"),
+        }
+    }
+
+    fn make_lib(path: &Path, lib_name: &str) {
+        let path = path.join(PathBuf::from(lib_name));
+        fs::create_dir_all(&path).unwrap();
+
+        fs::write(
+            path.join(PathBuf::from("Cargo.toml")),
+            format!("[package]\nname = \"{}\"\n", lib_name),
+        )
+        .expect("");
+    }
+}
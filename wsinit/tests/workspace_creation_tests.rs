@@ -39,7 +39,10 @@ mod tests {
             .expect_err("Expect new file to not be made in update mode");
 
         match toml_file_error {
-            Error::GenericCreationError(_) => {}
+            Error::Io {
+                operation: Operation::Read,
+                ..
+            } => {}
             _ => assert!(false, "Wrong error enum value"),
         }
     }
@@ -63,7 +66,7 @@ mod tests {
             .expect_err("Expect second file to not be created");
 
         match second_toml_file_error {
-            Error::FileAlreadyExists => {}
+            Error::FileAlreadyExists { .. } => {}
             _ => assert!(false, "Wrong error enum value"),
         }
 
@@ -147,7 +150,7 @@ members = [
             .expect_err("Expect file to not parse");
 
         match second_toml_file_error {
-            Error::ParseError => {}
+            Error::Parse { .. } => {}
             _ => assert!(false, "Wrong error enum value"),
         }
 
@@ -156,6 +159,31 @@ members = [
         assert_eq!("bad toml!", file_contents);
     }
 
+    #[test]
+    fn a_parse_error_reports_the_path_and_line() {
+        let test_root = ThreadTestPath::new_removed();
+        let path = test_root.to_str().unwrap();
+
+        let options = Options::new(path, FileExistsBehaviour::Halt);
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+
+        overwrite_file(&toml_file, "[workspace]\nmembers = \n");
+
+        let options = Options::new(path, FileExistsBehaviour::Update);
+        let error = Workspace::new(options)
+            .update_toml()
+            .expect_err("Expect file to not parse");
+
+        assert_eq!(toml_file.as_path(), error.path());
+        assert_eq!(Some(Operation::Parse), error.operation());
+        assert_eq!(format!("could not parse {}", toml_file), error.to_string());
+
+        let source = std::error::Error::source(&error).expect("Expect the toml error as the cause");
+        assert!(source.to_string().contains("line 2"), "{}", source);
+    }
+
     #[test]
     fn overwrite_an_existing_toml_requiring_truncate() {
         let test_root = ThreadTestPath::new_removed();
@@ -234,4 +262,86 @@ members = [
             file_contents
         );
     }
+
+    #[test]
+    fn overwrite_keeps_a_backup_that_undo_restores() {
+        let test_root = ThreadTestPath::new_removed();
+        let path = test_root.to_str().unwrap();
+
+        let options = Options::new(path, FileExistsBehaviour::Halt);
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+
+        overwrite_file(&toml_file, "Manually edited");
+
+        let options = Options::builder(path)
+            .existing_file_behaviour(FileExistsBehaviour::Overwrite)
+            .backup(true)
+            .build()
+            .unwrap();
+        let workspace = Workspace::new(options);
+        workspace
+            .update_toml()
+            .expect("Expect second file to be updated");
+
+        assert_ne!("Manually edited", read_file(&toml_file));
+        assert!(test_root.join("Cargo.toml.bak").exists());
+        assert!(!has_temp_files(&test_root));
+
+        workspace.undo().expect("Expect the backup to be restored");
+
+        assert_eq!("Manually edited", read_file(&toml_file));
+        assert!(!test_root.join("Cargo.toml.bak").exists());
+
+        match workspace.undo() {
+            Err(Error::NoBackup { .. }) => {}
+            _ => panic!("Wrong error enum value"),
+        }
+    }
+
+    #[test]
+    fn a_failed_write_leaves_no_temp_file() {
+        let test_root = ThreadTestPath::new_removed();
+        std::fs::create_dir_all(test_root.join("Cargo.toml/src")).unwrap();
+
+        RealFileSystem
+            .write(&test_root.join("Cargo.toml"), "[workspace]\n")
+            .expect_err("Expect a file not to replace a directory");
+
+        assert!(!has_temp_files(&test_root));
+    }
+
+    fn has_temp_files(dir: &std::path::Path) -> bool {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .any(|entry| entry.unwrap().path().to_string_lossy().ends_with(".wsinit"))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn overwrite_keeps_file_permissions() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let test_root = ThreadTestPath::new_removed();
+        let path = test_root.to_str().unwrap();
+
+        let options = Options::new(path, FileExistsBehaviour::Halt);
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+        fs::set_permissions(toml_file.as_path(), fs::Permissions::from_mode(0o640)).unwrap();
+
+        let options = Options::new(path, FileExistsBehaviour::Overwrite);
+        Workspace::new(options)
+            .update_toml()
+            .expect("Expect second file to be updated");
+
+        let mode = fs::metadata(toml_file.as_path())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(0o640, mode & 0o777);
+    }
 }
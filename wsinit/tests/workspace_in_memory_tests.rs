@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use cargo_wsinit::*;
+
+    #[test]
+    fn update_an_in_memory_tree() {
+        let fs = Arc::new(InMemoryFileSystem::new());
+        fs.add_file("/ws/lib1/Cargo.toml", "[package]\nname = \"lib1\"\n");
+        fs.add_file("/ws/libs/lib2/Cargo.toml", "[package]\nname = \"lib2\"\n");
+        fs.add_file(
+            "/ws/libs/lib2/nested/Cargo.toml",
+            "[package]\nname = \"nested\"\n",
+        );
+        fs.add_file(
+            "/ws/target/debug/Cargo.toml",
+            "[package]\nname = \"built\"\n",
+        );
+        fs.add_file("/ws/not-a-crate/Cargo.toml", "");
+
+        let options = Options::builder("/ws")
+            .file_system(fs.clone())
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert!(changes.written());
+        assert_eq!(1, changes.skipped().len());
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n    \"libs/lib2\",\n]\n",
+            fs.read_to_string(Path::new("/ws/Cargo.toml")).unwrap()
+        );
+        assert!(!Path::new("/ws").exists());
+    }
+
+    #[test]
+    fn move_root_package_in_memory() {
+        let fs = Arc::new(InMemoryFileSystem::new());
+        fs.add_file("/ws/Cargo.toml", "[package]\nname = \"app\"\n");
+        fs.add_file("/ws/src/main.rs", "fn main() {}\n");
+        fs.add_file("/ws/lib1/Cargo.toml", "[package]\nname = \"lib1\"\n");
+
+        let options = Options::builder("/ws")
+            .existing_file_behaviour(FileExistsBehaviour::Update)
+            .move_root_package("app")
+            .file_system(fs.clone())
+            .build()
+            .unwrap();
+        Workspace::new(options)
+            .update()
+            .expect("Expect the root package to move");
+
+        assert!(fs.is_file(Path::new("/ws/app/src/main.rs")));
+        assert!(!fs.exists(Path::new("/ws/src")));
+        assert!(fs
+            .read_to_string(Path::new("/ws/app/Cargo.toml"))
+            .unwrap()
+            .contains("name = \"app\""));
+        assert!(fs
+            .read_to_string(Path::new("/ws/Cargo.toml"))
+            .unwrap()
+            .contains("\"app\""));
+    }
+
+    #[test]
+    fn build_options_with_max_depth_and_dry_run() {
+        let fs = Arc::new(InMemoryFileSystem::new());
+        fs.add_file("/ws/lib1/Cargo.toml", "[package]\nname = \"lib1\"\n");
+        fs.add_file("/ws/libs/lib2/Cargo.toml", "[package]\nname = \"lib2\"\n");
+
+        let options = Options::builder(Path::new("/ws"))
+            .max_depth(1)
+            .dry_run(true)
+            .file_system(fs.clone())
+            .build()
+            .expect("Expect valid options");
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect the dry run to succeed");
+
+        assert_eq!(vec!["lib1".to_string()], changes.added());
+        assert!(!changes.written());
+        assert!(!fs.exists(Path::new("/ws/Cargo.toml")));
+    }
+
+    #[test]
+    fn an_unreadable_directory_is_a_warning_unless_strict() {
+        let fs = Arc::new(InMemoryFileSystem::new());
+        fs.add_file("/ws/lib1/Cargo.toml", "[package]\nname = \"lib1\"\n");
+        fs.add_file("/ws/locked/lib2/Cargo.toml", "[package]\nname = \"lib2\"\n");
+        fs.deny("/ws/locked");
+
+        let builder = || Options::builder("/ws").file_system(fs.clone());
+
+        let options = builder()
+            .dry_run(true)
+            .build()
+            .expect("Expect valid options");
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect the search to carry on past the locked directory");
+
+        assert_eq!(vec!["lib1".to_string()], changes.added());
+        assert_eq!(1, changes.discovery_warnings().len());
+        assert_eq!(Path::new("locked"), changes.discovery_warnings()[0].path());
+
+        let options = builder()
+            .strict(true)
+            .build()
+            .expect("Expect valid options");
+        match Workspace::new(options).update() {
+            Err(Error::Unsearched { warnings, .. }) => assert_eq!(1, warnings.len()),
+            result => panic!("Expect a strict search to fail, got {:?}", result),
+        }
+        assert!(!fs.exists(Path::new("/ws/Cargo.toml")));
+    }
+
+    #[test]
+    fn build_rejects_invalid_options() {
+        let error = |builder: OptionsBuilder| builder.build().err();
+
+        assert_eq!(Some(OptionsError::EmptyPath), error(Options::builder("")));
+        assert_eq!(
+            Some(OptionsError::ZeroMaxDepth),
+            error(Options::builder("/ws").max_depth(0))
+        );
+        assert_eq!(
+            Some(OptionsError::InvalidExclude("vendor/[".to_string())),
+            error(Options::builder("/ws").exclude("vendor/["))
+        );
+        assert_eq!(
+            Some(OptionsError::MoveRootPackageWithoutUpdate),
+            error(Options::builder("/ws").move_root_package("app"))
+        );
+        assert_eq!(
+            None,
+            error(
+                Options::builder("/ws")
+                    .existing_file_behaviour(FileExistsBehaviour::Update)
+                    .move_root_package("app")
+            )
+        );
+    }
+}
@@ -2,7 +2,7 @@ mod test_utils;
 
 #[cfg(test)]
 mod tests {
-    use std::fs::{self, File};
+    use std::fs;
     use std::path::{Path, PathBuf};
 
     use cargo_wsinit::*;
@@ -55,6 +55,99 @@ mod tests {
         assert!(!file_contents.contains("target"));
     }
 
+    #[test]
+    fn skip_ignored_paths() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_lib(&test_root, "scratch/lib2");
+        make_lib(&test_root, "lib3/generated");
+        make_file(&test_root, ".ignore", "/scratch\n");
+        make_file(&test_root, "lib3/.ignore", "generated/\n");
+
+        let options = Options::new(root_path, FileExistsBehaviour::Halt);
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+
+        let file_contents = read_file(&toml_file);
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n]\n",
+            file_contents
+        );
+    }
+
+    #[test]
+    fn skip_git_ignored_paths() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_lib(&test_root, "vendor/lib2");
+        // .gitignore files apply inside a git repository
+        fs::create_dir_all(test_root.join(".git")).unwrap();
+        make_file(&test_root, ".gitignore", "/vendor\n");
+
+        let options = Options::new(root_path, FileExistsBehaviour::Halt);
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+
+        let file_contents = read_file(&toml_file);
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n]\n",
+            file_contents
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follow_symlinked_directories() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "libs/lib1");
+        std::os::unix::fs::symlink("libs/lib1", test_root.join("linked")).unwrap();
+
+        let options = Options::new(root_path, FileExistsBehaviour::Halt);
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"libs/lib1\",\n    \"linked\",\n]\n",
+            read_file(&toml_file)
+        );
+    }
+
+    #[test]
+    fn include_ignored_paths_when_ignore_files_are_not_respected() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_lib(&test_root, "scratch/lib2");
+        make_file(&test_root, ".ignore", "/scratch\n");
+
+        let options = Options::builder(root_path)
+            .respect_ignore_files(false)
+            .build()
+            .unwrap();
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+
+        let file_contents = read_file(&toml_file);
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n    \"scratch/lib2\",\n]\n",
+            file_contents
+        );
+    }
+
     #[test]
     fn update_paths_in_cd() {
         let test_root = ThreadTestPath::new_removed();
@@ -106,12 +199,1092 @@ mod tests {
         );
     }
 
-    fn make_lib(path: &Path, lib_name: &str) {
-        let path = create_path(path, lib_name);
+    #[test]
+    fn update_merges_with_existing_members() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
 
-        let toml = path.join(PathBuf::from("Cargo.toml"));
+        make_lib(&test_root, "lib1");
+        make_lib(&test_root, "crates/a");
+        make_lib(&test_root, "crates/b");
+        make_file(
+            &test_root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\n    \"crates/*\", # all of them\n    \"gone\",\n]\n",
+        );
+
+        let options = Options::builder(root_path)
+            .existing_file_behaviour(FileExistsBehaviour::Update)
+            .missing_member_behaviour(MissingMemberBehaviour::Remove)
+            .build()
+            .unwrap();
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect file to be updated without issue");
+
+        let file_contents = read_file(&toml_file);
+
+        assert_eq!(
+            "[workspace]\nmembers = [\n    \"crates/*\", # all of them\n    \"lib1\",\n]\n",
+            file_contents
+        );
+    }
+
+    #[test]
+    fn update_recognises_members_written_another_way() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "a");
+        make_lib(&test_root, "b");
+        make_file(
+            &test_root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"./a\", \"b/\"]\n",
+        );
+
+        let options = Options::builder(root_path)
+            .existing_file_behaviour(FileExistsBehaviour::Update)
+            .missing_member_behaviour(MissingMemberBehaviour::Remove)
+            .build()
+            .unwrap();
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect file to be updated without issue");
+
+        let file_contents = read_file(&toml_file);
+
+        assert_eq!("[workspace]\nmembers = [\"./a\", \"b/\"]\n", file_contents);
+    }
+
+    #[test]
+    fn update_keeps_missing_members_by_default() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_file(
+            &test_root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\n    \"gone\",\n]\n",
+        );
+
+        let options = Options::new(root_path, FileExistsBehaviour::Update);
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect file to be updated without issue");
+
+        let file_contents = read_file(&toml_file);
+
+        assert_eq!(
+            "[workspace]\nmembers = [\n    \"gone\",\n    \"lib1\",\n]\n",
+            file_contents
+        );
+    }
+
+    #[test]
+    fn excluded_paths_are_skipped_and_recorded() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_lib(&test_root, "scratch/lib2");
+        make_lib(&test_root, "old-lib3");
+
+        let options = Options::builder(root_path)
+            .exclude("scratch")
+            .exclude("old-*")
+            .build()
+            .unwrap();
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+
+        let file_contents = read_file(&toml_file);
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n]\nexclude = [\n    \"old-*\",\n    \"scratch\",\n]\n",
+            file_contents
+        );
+    }
+
+    #[test]
+    fn existing_excludes_are_skipped_when_updating() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_lib(&test_root, "scratch/lib2");
+        make_file(
+            &test_root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\n    \"lib1\",\n]\nexclude = [\"scratch\"]\n",
+        );
+
+        let options = Options::new(root_path, FileExistsBehaviour::Update);
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect file to be updated without issue");
+
+        let file_contents = read_file(&toml_file);
+
+        assert_eq!(
+            "[workspace]\nmembers = [\n    \"lib1\",\n]\nexclude = [\"scratch\"]\n",
+            file_contents
+        );
+    }
+
+    #[test]
+    fn collapse_members_into_globs() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "crates/a");
+        make_lib(&test_root, "crates/b");
+        make_lib(&test_root, "tools/x");
+        make_lib(&test_root, "tools/y");
+        make_file(&test_root, "tools/README.md", "not a crate");
+
+        let options = Options::builder(root_path)
+            .collapse_globs(true)
+            .build()
+            .unwrap();
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+
+        let file_contents = read_file(&toml_file);
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"crates/*\",\n    \"tools/x\",\n    \"tools/y\",\n]\n",
+            file_contents
+        );
+    }
+
+    #[test]
+    fn collapsed_glob_replaces_explicit_members_when_updating() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "crates/a");
+        make_lib(&test_root, "crates/b");
+        make_file(
+            &test_root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\n    \"crates/a\",\n]\n",
+        );
+
+        let options = Options::builder(root_path)
+            .existing_file_behaviour(FileExistsBehaviour::Update)
+            .collapse_globs(true)
+            .build()
+            .unwrap();
+        let toml_file = Workspace::new(options)
+            .update_toml()
+            .expect("Expect file to be updated without issue");
+
+        let file_contents = read_file(&toml_file);
+
+        assert_eq!(
+            "[workspace]\nmembers = [\n    \"crates/*\",\n]\n",
+            file_contents
+        );
+    }
+
+    #[test]
+    fn skip_manifests_that_are_not_packages() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_file(&test_root, "empty/Cargo.toml", "");
+        make_file(&test_root, "broken/Cargo.toml", "bad toml!");
+        make_file(&test_root, "deps/Cargo.toml", "[dependencies]\n");
+        make_file(
+            &test_root,
+            "inner/Cargo.toml",
+            "[workspace]\nmembers = []\n",
+        );
+
+        let options = Options::new(root_path, FileExistsBehaviour::Halt);
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n]\n",
+            read_file(changes.toml())
+        );
+
+        let mut skipped: Vec<(String, SkipReason)> = changes
+            .skipped()
+            .iter()
+            .map(|s| (s.path().to_str().unwrap().to_string(), s.reason().clone()))
+            .collect();
+        skipped.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(4, skipped.len());
+        assert!(matches!(skipped[0].1, SkipReason::Unparsable(_)));
+        assert_eq!(
+            vec![
+                ("deps".to_string(), SkipReason::NoPackage),
+                ("empty".to_string(), SkipReason::Empty),
+                ("inner".to_string(), SkipReason::NestedWorkspace),
+            ],
+            skipped[1..]
+        );
+    }
+
+    #[test]
+    fn absorb_nested_virtual_workspace() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_lib(&test_root, "inner/a");
+        make_lib(&test_root, "inner/b");
+        make_file(
+            &test_root,
+            "inner/Cargo.toml",
+            "[workspace]\nmembers = [\"a\", \"b\"]\n",
+        );
+
+        let options = Options::builder(root_path)
+            .nested_workspace_behaviour(NestedWorkspaceBehaviour::Absorb)
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"inner/a\",\n    \"inner/b\",\n    \"lib1\",\n]\n",
+            read_file(changes.toml())
+        );
+        assert!(changes.skipped().is_empty());
+        assert_eq!(1, changes.manifest_changes().len());
+        assert!(!test_root.join("inner/Cargo.toml").exists());
+    }
+
+    #[test]
+    fn absorb_nested_workspace_with_package() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "inner/a");
+        make_file(
+            &test_root,
+            "inner/Cargo.toml",
+            "[package]\nname = \"inner\"\n\n[workspace]\nmembers = [\"a\"]\n",
+        );
+
+        let options = Options::builder(root_path)
+            .nested_workspace_behaviour(NestedWorkspaceBehaviour::Absorb)
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"inner\",\n    \"inner/a\",\n]\n",
+            read_file(changes.toml())
+        );
+        assert_eq!(
+            "[package]\nname = \"inner\"",
+            fs::read_to_string(test_root.join("inner/Cargo.toml"))
+                .unwrap()
+                .trim()
+        );
+    }
+
+    #[test]
+    fn exclude_nested_workspace_whose_members_inherit_from_it() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_file(
+            &test_root,
+            "inner/a/Cargo.toml",
+            "[package]\nname = \"a\"\nedition.workspace = true\n\n[dependencies]\nserde.workspace = true\n",
+        );
+        let inner = "[workspace]\nmembers = [\"a\"]\n\n[workspace.package]\nedition = \"2021\"\n\n[workspace.dependencies]\nserde = \"1\"\n";
+        make_file(&test_root, "inner/Cargo.toml", inner);
+
+        let options = Options::builder(root_path)
+            .nested_workspace_behaviour(NestedWorkspaceBehaviour::Absorb)
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n]\nexclude = [\n    \"inner\",\n]\n",
+            read_file(changes.toml())
+        );
+        assert_eq!(1, changes.skipped().len());
+        assert_eq!(
+            &SkipReason::NestedWorkspaceSettings(vec![
+                "workspace.package".to_string(),
+                "workspace.dependencies".to_string()
+            ]),
+            changes.skipped()[0].reason()
+        );
+        assert!(changes.manifest_changes().is_empty());
+        assert_eq!(
+            inner,
+            fs::read_to_string(test_root.join("inner/Cargo.toml")).unwrap()
+        );
+    }
+
+    #[test]
+    fn exclude_nested_workspace() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_lib(&test_root, "inner/a");
+        make_file(
+            &test_root,
+            "inner/Cargo.toml",
+            "[workspace]\nmembers = [\"a\"]\n",
+        );
+
+        let options = Options::builder(root_path)
+            .nested_workspace_behaviour(NestedWorkspaceBehaviour::Exclude)
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n]\nexclude = [\n    \"inner\",\n]\n",
+            read_file(changes.toml())
+        );
+        assert!(changes.skipped().is_empty());
+        assert!(changes.manifest_changes().is_empty());
+        assert!(test_root.join("inner/Cargo.toml").exists());
+    }
+
+    #[test]
+    fn update_single_package_root() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_file(&test_root, "Cargo.toml", "[package]\nname = \"app\"\n");
+        make_file(&test_root, "src/main.rs", "fn main() {}\n");
+        make_lib(&test_root, "lib1");
+
+        let options = Options::new(root_path, FileExistsBehaviour::Update);
+        let toml = Workspace::new(options)
+            .update_toml()
+            .expect("Expect file to be updated without issue");
+
+        assert_eq!(
+            "[package]\nname = \"app\"\n\n[workspace]\nmembers = [\n    \".\",\n    \"lib1\",\n]\n",
+            read_file(&toml)
+        );
+
+        // already a member on the next update
+        let options = Options::new(root_path, FileExistsBehaviour::Update);
+        let changes = Workspace::new(options).check_toml().unwrap();
+        assert!(!changes.is_stale());
+    }
+
+    #[test]
+    fn move_root_package_into_sub_directory() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_file(
+            &test_root,
+            "Cargo.toml",
+            "[package]\nname = \"app\"\nreadme = \"README.md\"\n\n[dependencies]\nlib1 = { path = \"lib1\" }\n\n[profile.release]\nlto = true\n",
+        );
+        make_file(&test_root, "README.md", "");
+        make_file(&test_root, "src/main.rs", "fn main() {}\n");
+        make_lib(&test_root, "lib1");
+
+        let options = Options::builder(root_path)
+            .existing_file_behaviour(FileExistsBehaviour::Update)
+            .move_root_package("crates/app")
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect file to be updated without issue");
+
+        assert_eq!(
+            "[profile.release]\nlto = true\n\n[workspace]\nmembers = [\n    \"crates/app\",\n    \"lib1\",\n]\n",
+            read_file(changes.toml())
+        );
+        assert_eq!(
+            "[package]\nname = \"app\"\nreadme = \"../../README.md\"\n\n[dependencies]\nlib1 = { path = \"../../lib1\" }\n",
+            fs::read_to_string(test_root.join("crates/app/Cargo.toml")).unwrap()
+        );
+        assert!(test_root.join("crates/app/src/main.rs").is_file());
+        assert!(!test_root.join("src").exists());
+        assert!(test_root.join("README.md").is_file());
+    }
+
+    #[test]
+    fn move_root_package_needs_a_package() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_file(&test_root, "Cargo.toml", "[workspace]\nmembers = []\n");
+
+        let options = Options::builder(root_path)
+            .existing_file_behaviour(FileExistsBehaviour::Update)
+            .move_root_package("app")
+            .build()
+            .unwrap();
+        let result = Workspace::new(options).update();
+
+        assert!(matches!(result, Err(Error::NoRootPackage { .. })));
+    }
+
+    #[test]
+    fn hoist_shared_dependencies() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_file(
+            &test_root,
+            "lib1/Cargo.toml",
+            "[package]\nname = \"lib1\"\n\n[dependencies]\nlog = { version = \"0.4\", features = [\"std\"] }\nrand = \"0.7\"\nserde = \"1.0\"\n",
+        );
+        make_file(
+            &test_root,
+            "lib2/Cargo.toml",
+            "[package]\nname = \"lib2\"\n\n[dependencies]\nlog = \"0.4\"\nrand = \"0.8\"\nregex = \"1\"\n\n[dev-dependencies]\nserde = \"1.0\"\n",
+        );
+
+        let options = Options::builder(root_path)
+            .hoist_dependencies(true)
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n    \"lib2\",\n]\n\n[workspace.dependencies]\nlog = \"0.4\"\nserde = \"1.0\"\n",
+            read_file(changes.toml())
+        );
+        assert_eq!(
+            "[package]\nname = \"lib1\"\n\n[dependencies]\nlog = { workspace = true, features = [\"std\"] }\nrand = \"0.7\"\nserde = { workspace = true }\n",
+            fs::read_to_string(test_root.join("lib1/Cargo.toml")).unwrap()
+        );
+        assert_eq!(
+            "[package]\nname = \"lib2\"\n\n[dependencies]\nlog = { workspace = true }\nrand = \"0.8\"\nregex = \"1\"\n\n[dev-dependencies]\nserde = { workspace = true }\n",
+            fs::read_to_string(test_root.join("lib2/Cargo.toml")).unwrap()
+        );
+
+        assert_eq!(1, changes.conflicts().len());
+        assert_eq!("dependencies.rand", changes.conflicts()[0].key());
+        assert_eq!(
+            "dependencies.rand: lib1 has \"0.7\", lib2 has \"0.8\"",
+            changes.conflicts()[0].to_string()
+        );
+    }
+
+    #[test]
+    fn do_not_hoist_a_dependency_that_is_a_path_dependency_elsewhere() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        let registry = "[package]\nname = \"lib\"\n\n[dependencies]\nfoo = \"1\"\n";
+        let path = "[package]\nname = \"c\"\n\n[dependencies]\nfoo = { path = \"../foo\" }\n";
+        make_file(&test_root, "a/Cargo.toml", registry);
+        make_file(&test_root, "b/Cargo.toml", registry);
+        make_file(&test_root, "c/Cargo.toml", path);
+        make_file(
+            &test_root,
+            "foo/Cargo.toml",
+            "[package]\nname = \"foo\"\nversion = \"2.0.0\"\n",
+        );
+
+        let options = Options::builder(root_path)
+            .hoist_dependencies(true)
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert!(!read_file(changes.toml()).contains("[workspace.dependencies]"));
+        assert_eq!(
+            registry,
+            fs::read_to_string(test_root.join("a/Cargo.toml")).unwrap()
+        );
+        assert_eq!(
+            path,
+            fs::read_to_string(test_root.join("c/Cargo.toml")).unwrap()
+        );
+
+        assert_eq!(1, changes.conflicts().len());
+        assert_eq!(
+            "dependencies.foo: a has \"1\", b has \"1\", c has { path = \"../foo\" }",
+            changes.conflicts()[0].to_string()
+        );
+    }
+
+    #[test]
+    fn inherit_shared_package_fields() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_file(
+            &test_root,
+            "lib1/Cargo.toml",
+            "[package]\nname = \"lib1\"\nedition = \"2018\"\nlicense = \"MIT\"\n",
+        );
+        make_file(
+            &test_root,
+            "lib2/Cargo.toml",
+            "[package]\nname = \"lib2\"\nedition = \"2018\"\nlicense = \"Apache-2.0\"\n",
+        );
+
+        let options = Options::builder(root_path)
+            .inherit_package_fields(true)
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n    \"lib2\",\n]\n\n[workspace.package]\nedition = \"2018\"\n",
+            read_file(changes.toml())
+        );
+        assert_eq!(
+            "[package]\nname = \"lib1\"\nedition = { workspace = true }\nlicense = \"MIT\"\n",
+            fs::read_to_string(test_root.join("lib1/Cargo.toml")).unwrap()
+        );
+        assert_eq!(
+            "package.license: lib1 has \"MIT\", lib2 has \"Apache-2.0\"",
+            changes.conflicts()[0].to_string()
+        );
+        assert_eq!(1, changes.conflicts().len());
+    }
+
+    #[test]
+    fn path_dependencies_between_members() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_file(
+            &test_root,
+            "crates/lib1/Cargo.toml",
+            "[package]\nname = \"lib1\"\n\n[dependencies]\nlib2 = { path = \"../../lib2\", version = \"0.2\", features = [\"x\"] }\nother = { path = \"../../../other\" }\n",
+        );
+        make_file(
+            &test_root,
+            "lib2/Cargo.toml",
+            "[package]\nname = \"lib2\"\nversion = \"0.2.0\"\n",
+        );
+
+        let options = Options::builder(root_path)
+            .workspace_path_dependencies(true)
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"crates/lib1\",\n    \"lib2\",\n]\n\n[workspace.dependencies]\nlib2 = { path = \"lib2\", version = \"0.2.0\" }\n",
+            read_file(changes.toml())
+        );
+        assert_eq!(
+            "[package]\nname = \"lib1\"\n\n[dependencies]\nlib2 = { workspace = true, features = [\"x\"] }\nother = { path = \"../../../other\" }\n",
+            fs::read_to_string(test_root.join("crates/lib1/Cargo.toml")).unwrap()
+        );
+
+        let external = changes.external_path_dependencies();
+        assert_eq!(1, external.len());
+        assert_eq!("crates/lib1", external[0].member());
+        assert_eq!("other", external[0].name());
+    }
+
+    #[test]
+    fn resolver_follows_member_editions() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_file(
+            &test_root,
+            "lib2/Cargo.toml",
+            "[package]\nname = \"lib2\"\nedition = \"2021\"\n",
+        );
+
+        let options = Options::new(root_path, FileExistsBehaviour::Halt);
+        let toml = Workspace::new(options)
+            .update_toml()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n    \"lib2\",\n]\nresolver = \"2\"\n",
+            read_file(&toml)
+        );
+
+        let options = Options::builder(root_path)
+            .existing_file_behaviour(FileExistsBehaviour::Overwrite)
+            .resolver(Resolver::V3)
+            .build()
+            .unwrap();
+        let toml = Workspace::new(options).update_toml().unwrap();
+
+        assert!(read_file(&toml).contains("resolver = \"3\""));
+    }
+
+    #[test]
+    fn moved_root_package_keeps_its_resolver() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_file(
+            &test_root,
+            "Cargo.toml",
+            "[package]\nname = \"app\"\nedition = \"2021\"\n",
+        );
+        make_lib(&test_root, "lib1");
+
+        let options = Options::builder(root_path)
+            .existing_file_behaviour(FileExistsBehaviour::Update)
+            .move_root_package("app")
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options).update().unwrap();
+
+        assert_eq!(None, changes.resolver_mismatch());
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"app\",\n    \"lib1\",\n]\nresolver = \"2\"\n",
+            read_file(changes.toml())
+        );
+    }
+
+    #[test]
+    fn update_reports_an_old_resolver() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_file(
+            &test_root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"lib1\"]\n",
+        );
+        make_file(
+            &test_root,
+            "lib1/Cargo.toml",
+            "[package]\nname = \"lib1\"\nedition = \"2021\"\n",
+        );
+
+        let options = Options::new(root_path, FileExistsBehaviour::Update);
+        let changes = Workspace::new(options).update().unwrap();
+
+        let mismatch = changes
+            .resolver_mismatch()
+            .expect("Expect a resolver mismatch");
+        assert_eq!(Resolver::V1, mismatch.current());
+        assert_eq!(Resolver::V2, mismatch.expected());
+        assert!(!changes.proposed().contains("resolver"));
+    }
+
+    #[test]
+    fn consolidate_member_profiles() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_file(
+            &test_root,
+            "lib1/Cargo.toml",
+            "[package]\nname = \"lib1\"\n\n[profile.release]\nlto = true\nopt-level = 3\n",
+        );
+        make_file(
+            &test_root,
+            "lib2/Cargo.toml",
+            "[package]\nname = \"lib2\"\n\n[profile.release]\nlto = true\nopt-level = \"s\"\n",
+        );
+
+        let options = Options::builder(root_path)
+            .consolidate_profiles(true)
+            .build()
+            .unwrap();
+        let workspace = Workspace::new(options);
+
+        let preview = workspace.check_toml().unwrap();
+        assert_eq!(2, preview.manifest_changes().len());
+        assert!(!test_root.join("Cargo.toml").exists());
+
+        let changes = workspace
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"lib1\",\n    \"lib2\",\n]\n\n[profile.release]\nlto = true\n",
+            read_file(changes.toml())
+        );
+        assert_eq!(
+            "[package]\nname = \"lib1\"\n\n[profile.release]\nopt-level = 3\n",
+            fs::read_to_string(test_root.join("lib1/Cargo.toml")).unwrap()
+        );
+        assert_eq!(
+            "profile.release.opt-level: lib1 has 3, lib2 has \"s\"",
+            changes.conflicts()[0].to_string()
+        );
+    }
+
+    #[test]
+    fn consolidate_member_patches() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_file(
+            &test_root,
+            "crates/lib1/Cargo.toml",
+            "[package]\nname = \"lib1\"\n\n[patch.crates-io]\nfoo = { path = \"../../vendor/foo\" }\n",
+        );
+        make_file(
+            &test_root,
+            "lib2/Cargo.toml",
+            "[package]\nname = \"lib2\"\n\n[patch.crates-io]\nfoo = { path = \"../vendor/foo\" }\n",
+        );
+
+        let options = Options::builder(root_path)
+            .consolidate_patches(true)
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(
+            "[workspace]\n\nmembers = [\n    \"crates/lib1\",\n    \"lib2\",\n]\n\n[patch.crates-io]\nfoo = { path = \"vendor/foo\" }\n",
+            read_file(changes.toml())
+        );
+        assert_eq!(
+            "[package]\nname = \"lib2\"\n",
+            fs::read_to_string(test_root.join("lib2/Cargo.toml")).unwrap()
+        );
+    }
+
+    #[test]
+    fn conflicting_member_patches_fail() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_file(
+            &test_root,
+            "lib1/Cargo.toml",
+            "[package]\nname = \"lib1\"\n\n[patch.crates-io]\nfoo = { path = \"../foo\" }\n",
+        );
+        make_file(
+            &test_root,
+            "lib2/Cargo.toml",
+            "[package]\nname = \"lib2\"\n\n[patch.crates-io]\nfoo = { git = \"https://example.com/foo\" }\n",
+        );
+
+        let options = Options::builder(root_path)
+            .consolidate_patches(true)
+            .build()
+            .unwrap();
+        let result = Workspace::new(options).update();
+
+        match result {
+            Err(Error::ConflictingPatch { conflict, .. }) => {
+                assert_eq!("patch.crates-io.foo", conflict.key())
+            }
+            _ => panic!("Expect conflicting patches to fail"),
+        }
+        assert!(!test_root.join("Cargo.toml").exists());
+    }
+
+    #[test]
+    fn merge_member_lockfiles() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+        let lock = |name: &str, version: &str| {
+            format!(
+                "version = 3\n\n[[package]]\nname = \"{}\"\nversion = \"0.1.0\"\ndependencies = [\n \"log\",\n]\n\n[[package]]\nname = \"log\"\nversion = \"{}\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+                name, version
+            )
+        };
+
+        make_lib(&test_root, "lib1");
+        make_lib(&test_root, "lib2");
+        make_file(&test_root, "lib1/Cargo.lock", &lock("lib1", "0.4.8"));
+        make_file(&test_root, "lib2/Cargo.lock", &lock("lib2", "0.4.11"));
+
+        let options = Options::builder(root_path)
+            .merge_lockfiles(true)
+            .build()
+            .unwrap();
+        Workspace::new(options)
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        let merged = fs::read_to_string(test_root.join("Cargo.lock")).unwrap();
+        assert!(merged.contains("name = \"log\"\nversion = \"0.4.11\"\n"));
+        assert!(!merged.contains("0.4.8"));
+        assert!(!test_root.join("lib1/Cargo.lock").exists());
+        assert!(!test_root.join("lib2/Cargo.lock").exists());
+    }
+
+    #[test]
+    fn list_and_remove_member_targets() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_lib(&test_root, "lib2");
+        make_file(&test_root, "lib1/target/debug/lib1", "0123456789");
+
+        let workspace = Workspace::new(Options::new(root_path, FileExistsBehaviour::Halt));
+        workspace
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        let targets = workspace.stale_targets().unwrap();
+        assert_eq!(1, targets.len());
+        assert_eq!(test_root.join("lib1").join("target"), targets[0].path());
+        assert_eq!(10, targets[0].size());
+
+        targets[0].remove().unwrap();
+        assert!(!test_root.join("lib1/target").exists());
+        assert!(workspace.stale_targets().unwrap().is_empty());
+    }
+
+    #[test]
+    fn report_created_then_unchanged_then_failed() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_non_lib(&test_root, "lib2");
+
+        let report =
+            Workspace::new(Options::new(root_path, FileExistsBehaviour::Halt)).update_report();
+        assert_eq!(Some(Action::Created), report.action());
+        assert_eq!(vec!["lib1"], report.added());
+        assert!(report.to_json().contains("\"action\":\"created\""));
+
+        let report =
+            Workspace::new(Options::new(root_path, FileExistsBehaviour::Update)).update_report();
+        assert_eq!(Some(Action::Unchanged), report.action());
+        assert!(report.added().is_empty());
+
+        let report =
+            Workspace::new(Options::new(root_path, FileExistsBehaviour::Halt)).update_report();
+        assert_eq!(None, report.action());
+        assert_eq!("file_already_exists", report.error().unwrap().kind());
+        assert!(report
+            .to_json()
+            .contains("\"error\":{\"kind\":\"file_already_exists\""));
+    }
+
+    #[test]
+    fn report_members_before_and_after() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_lib(&test_root, "lib3");
+        make_file(
+            &test_root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\n    \"lib1\",\n    \"lib2\",\n]\n",
+        );
+
+        let options = Options::builder(root_path)
+            .existing_file_behaviour(FileExistsBehaviour::Update)
+            .missing_member_behaviour(MissingMemberBehaviour::Remove)
+            .build()
+            .unwrap();
+        let report = Workspace::new(options).update_report();
+
+        assert_eq!(Some(Action::Updated), report.action());
+        assert_eq!(vec!["lib1", "lib2"], report.before());
+        assert_eq!(vec!["lib1", "lib3"], report.after());
+        assert_eq!(vec!["lib3"], report.added());
+        assert_eq!(vec!["lib2"], report.removed());
+        assert_eq!(vec!["lib1"], report.kept());
+        assert_eq!(
+            fs::read_to_string(test_root.join("Cargo.toml")).unwrap(),
+            report.contents().unwrap()
+        );
+    }
+
+    #[test]
+    fn report_a_dry_run_undo_and_cleaned_targets() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        make_file(&test_root, "lib1/target/debug/lib1", "0123456789");
+
+        let options = Options::builder(root_path)
+            .dry_run(true)
+            .build()
+            .expect("Expect valid options");
+        let report = Workspace::new(options).update_report();
+        assert_eq!(Some(Action::Created), report.action());
+        assert!(report.dry_run());
+        assert!(report.to_json().contains("\"dry_run\":true"));
+        assert!(!test_root.join("Cargo.toml").exists());
+
+        let workspace = Workspace::new(Options::new(root_path, FileExistsBehaviour::Halt));
+        let report = workspace.clean_targets_report(workspace.update_report());
+        assert_eq!(Some(Action::Created), report.action());
+        assert!(!report.dry_run());
+        assert_eq!(
+            vec![test_root.join("lib1/target")],
+            report.deleted_targets()
+        );
+        assert!(!test_root.join("lib1/target").exists());
+
+        make_lib(&test_root, "lib2");
+        let options = Options::builder(root_path)
+            .existing_file_behaviour(FileExistsBehaviour::Update)
+            .backup(true)
+            .build()
+            .unwrap();
+        let workspace = Workspace::new(options);
+        workspace.update().expect("Expect update to succeed");
+
+        let report = workspace.undo_report();
+        assert_eq!(Some(Action::Restored), report.action());
+        assert_eq!(vec!["lib1", "lib2"], report.before());
+        assert_eq!(vec!["lib1"], report.after());
+        assert!(report.to_json().contains("\"action\":\"restored\""));
+
+        let report = workspace.undo_report();
+        assert_eq!("no_backup", report.error().unwrap().kind());
+    }
+
+    #[test]
+    fn update_does_not_rewrite_an_unchanged_file() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+
+        let changes = Workspace::new(Options::new(root_path, FileExistsBehaviour::Halt))
+            .update()
+            .expect("Expect new file to be made without issue");
+        assert!(changes.written());
+        let modified = fs::metadata(test_root.join("Cargo.toml"))
+            .unwrap()
+            .modified()
+            .unwrap();
+
+        let options = Options::builder(root_path)
+            .existing_file_behaviour(FileExistsBehaviour::Overwrite)
+            .backup(true)
+            .build()
+            .unwrap();
+        let changes = Workspace::new(options)
+            .update()
+            .expect("Expect update to succeed");
+
+        assert!(!changes.written());
+        assert!(!test_root.join("Cargo.toml.bak").exists());
+        assert_eq!(
+            modified,
+            fs::metadata(test_root.join("Cargo.toml"))
+                .unwrap()
+                .modified()
+                .unwrap()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skip_paths_that_are_not_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        make_lib(&test_root, "lib1");
+        let invalid = Path::new("libs").join(OsStr::from_bytes(b"lib\xff"));
+        let invalid_dir = test_root.join(&invalid);
+        fs::create_dir_all(&invalid_dir).unwrap();
+        fs::write(
+            invalid_dir.join("Cargo.toml"),
+            "[package]\nname = \"lib2\"\n",
+        )
+        .unwrap();
+
+        let changes = Workspace::new(Options::new(root_path, FileExistsBehaviour::Halt))
+            .update()
+            .expect("Expect new file to be made without issue");
+
+        assert_eq!(vec!["lib1".to_string()], changes.added());
+        assert_eq!(1, changes.skipped().len());
+        assert_eq!(invalid.as_path(), changes.skipped()[0].path());
+        assert_eq!(&SkipReason::NonUtf8Path, changes.skipped()[0].reason());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn escape_member_paths_in_the_toml_file() {
+        let test_root = ThreadTestPath::new_removed();
+        let root_path = test_root.to_str().unwrap();
+
+        let names = [
+            "'quoted'",
+            "a'''\"\"\"b",
+            "back\\slash",
+            "not\\na_newline",
+            "quote\"d",
+            "tab\tbed",
+        ];
+        for name in &names {
+            make_file(
+                &test_root,
+                &format!("libs/{}/Cargo.toml", name),
+                "[package]\nname = \"lib\"\n",
+            );
+        }
+
+        let report =
+            Workspace::new(Options::new(root_path, FileExistsBehaviour::Halt)).update_report();
+
+        assert_eq!(None, report.error());
+        assert_eq!(
+            names
+                .iter()
+                .map(|name| format!("libs/{}", name))
+                .collect::<Vec<_>>(),
+            report.after()
+        );
+
+        let changes = Workspace::new(Options::new(root_path, FileExistsBehaviour::Update))
+            .check_toml()
+            .expect("Expect the file to be checked without issue");
+
+        assert!(!changes.is_stale());
+    }
+
+    fn make_lib(path: &Path, lib_name: &str) {
+        let path = create_path(path, lib_name);
+
+        let toml = path.join(PathBuf::from("Cargo.toml"));
+
+        fs::write(toml, format!("[package]\nname = \"{}\"\n", lib_name)).expect("");
+    }
 
-        File::create(toml).expect("");
+    fn make_file(path: &Path, file_name: &str, contents: &str) {
+        let file = path.join(PathBuf::from(file_name));
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, contents).unwrap();
     }
 
     fn make_non_lib(path: &Path, lib_name: &str) {
@@ -119,7 +1292,7 @@ mod tests {
 
         let toml = path.join(PathBuf::from("SomeFile.txt"));
 
-        File::create(toml).expect("");
+        fs::write(toml, format!("[package]\nname = \"{}\"\n", lib_name)).expect("");
     }
 
     fn create_path(path: &Path, lib_name: &str) -> PathBuf {